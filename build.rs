@@ -0,0 +1,136 @@
+//! Embeds `assets/`: every file gets a content-hashed name and, when it
+//! compresses well, gzip and brotli variants. The table is written to
+//! `$OUT_DIR/assets.rs` and included by `src/assets.rs`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+
+const ASSETS_DIR: &str = "assets";
+
+/// Variants must save at least this share of the original to be kept.
+const MIN_SAVING: f64 = 0.1;
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ASSETS_DIR);
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let mut files = Vec::new();
+    collect(Path::new(ASSETS_DIR), &mut files);
+    files.sort();
+
+    let mut table = String::from("&[\n");
+    for file in files {
+        let path = file.strip_prefix(ASSETS_DIR).unwrap().to_str().unwrap().replace('\\', "/");
+        let body = fs::read(&file).unwrap();
+        let hash = &hex::encode(Sha256::digest(&body))[..16];
+        let (gzip, brotli) = if compressible(&path) {
+            let gzip = gzip(&body);
+            let brotli = brotli(&body);
+            (
+                worth_it(&body, &gzip).then(|| write_variant(&out_dir, hash, "gz", &gzip)),
+                worth_it(&body, &brotli).then(|| write_variant(&out_dir, hash, "br", &brotli)),
+            )
+        } else {
+            (None, None)
+        };
+        let source = fs::canonicalize(&file).unwrap();
+
+        writeln!(table, "    Asset {{").unwrap();
+        writeln!(table, "        path: {:?},", path).unwrap();
+        writeln!(table, "        hashed_path: {:?},", hashed_path(&path, hash)).unwrap();
+        writeln!(table, "        content_type: {:?},", content_type(&path)).unwrap();
+        writeln!(table, "        hash: {:?},", hash).unwrap();
+        writeln!(table, "        body: include_bytes!({:?}),", source).unwrap();
+        writeln!(table, "        gzip: {},", include(gzip)).unwrap();
+        writeln!(table, "        brotli: {},", include(brotli)).unwrap();
+        writeln!(table, "    }},").unwrap();
+    }
+    table.push(']');
+
+    fs::write(out_dir.join("assets.rs"), table).unwrap();
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// `css/style.css` becomes `css/style.<hash>.css`.
+fn hashed_path(path: &str, hash: &str) -> String {
+    let (dir, name) = path.rsplit_once('/').map_or(("", path), |(dir, name)| (dir, name));
+    let name = match name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}.{}.{}", stem, hash, ext),
+        None => format!("{}.{}", name, hash),
+    };
+    if dir.is_empty() { name } else { format!("{}/{}", dir, name) }
+}
+
+fn extension(path: &str) -> &str {
+    path.rsplit_once('.').map_or("", |(_, ext)| ext)
+}
+
+fn content_type(path: &str) -> &'static str {
+    match extension(path) {
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Formats that are not compressed already.
+fn compressible(path: &str) -> bool {
+    matches!(extension(path), "css" | "js" | "json" | "webmanifest" | "svg" | "ico" | "txt")
+}
+
+fn worth_it(body: &[u8], compressed: &[u8]) -> bool {
+    (compressed.len() as f64) < body.len() as f64 * (1.0 - MIN_SAVING)
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(body: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: 11,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut &body[..], &mut output, &params).unwrap();
+    output
+}
+
+fn write_variant(out_dir: &Path, hash: &str, suffix: &str, body: &[u8]) -> PathBuf {
+    let path = out_dir.join(format!("asset-{}.{}", hash, suffix));
+    fs::write(&path, body).unwrap();
+    path
+}
+
+fn include(path: Option<PathBuf>) -> String {
+    match path {
+        Some(path) => format!("Some(include_bytes!({:?}))", path),
+        None => "None".to_string(),
+    }
+}
@@ -0,0 +1,260 @@
+# Shown in the language switcher, in the language itself.
+language-name = English
+
+## Layout
+
+site-name = Rust example
+base-placeholder = Use this document as a way to quick start any new project.
+page-load-time = Page load time
+page-load-calculating = calculating...
+close = Close
+impersonation-banner = You ({ $admin }) are impersonating { $user } ({ $email }).
+impersonation-stop = stop impersonating
+
+nav-toggle = Toggle navigation
+nav-user = User
+nav-admin = Admin
+nav-audit = Audit
+nav-directory = Members
+nav-auth = Auth
+nav-login = login
+nav-logout = logout
+nav-signup = signup
+nav-email-verify-resend = resend verification email
+nav-reset-password = reset password
+
+footer-language = Language
+footer-features = Features
+footer-cool-stuff = Cool stuff
+footer-random-feature = Random feature
+footer-team-feature = Team feature
+footer-developers = Stuff for developers
+footer-another-one = Another one
+footer-last-time = Last time
+footer-resources = Resources
+footer-resource = Resource
+footer-resource-name = Resource name
+footer-another-resource = Another resource
+footer-final-resource = Final resource
+footer-about = About
+footer-team = Team
+footer-locations = Locations
+footer-privacy = Privacy
+footer-terms = Terms
+
+## Home
+
+index-title = home
+index-heading = Pricing
+index-lead = Quickly build this Bootstrap example. It’s built with default Bootstrap components and utilities with little customization.
+index-per-month = /mo
+index-plan-free = Free
+index-plan-pro = Pro
+index-plan-enterprise = Enterprise
+index-users-included = { $count } users included
+index-storage = { $size } GB of storage
+index-email-support = Email support
+index-priority-support = Priority email support
+index-phone-support = Phone and email support
+index-help-center = Help center access
+index-sign-up-free = Sign up for free
+index-get-started = Get started
+index-contact-us = Contact us
+
+## Forms
+
+form-email = Email address
+form-password = Password
+form-username = Username
+form-login = Username or email
+form-submit = submit
+form-send-email = send to email
+
+## Accounts
+
+login-title = Login
+login-heading = Please sign in
+login-remember-me = Remember me
+login-submit = Sign in
+login-reset-password = reset password
+login-invalid-credentials = Invalid username, email or password.
+login-account-disabled = This account is disabled.
+
+logout-title = logout
+logout-submit = logout
+
+signup-title = register
+signup-heading = Please sign up
+signup-submit = Sign Up
+signup-login = login
+signup-username-taken = This username is already taken.
+
+verify-resend-title = resend verification
+verify-resend-heading = Verify email
+verify-sent-title = check your email
+verify-sent-heading = Check your email
+verify-sent-body = If an account can be used with this address, we have sent it a message with the next steps.
+
+reset-password-title = reset password
+reset-password-heading = Please enter your username or email
+reset-password-confirm-heading = Choose a new password
+reset-password-confirm-submit = change
+
+## Profile
+
+profile-user = user: { $email }
+profile-title = user { $username }
+profile-id = id: { $id }
+profile-email = email: { $email }
+profile-username = username: { $username }
+profile-created-at = created: { $date }
+profile-updated-at = updated: { $date }
+profile-edit = Edit profile
+profile-tokens = Access tokens
+profile-delete = Delete account
+profile-public = Public profile
+
+public-title = { $username }
+public-joined = joined { $date }
+public-no-bio = No bio yet.
+public-edit = edit your public profile
+
+public-settings-title = public profile
+public-settings-heading = public profile
+public-settings-bio = about you
+public-settings-visibility = who can see your page
+public-settings-view = view your page
+
+visibility-public = everyone
+visibility-members = signed-in users
+visibility-hidden = only you
+
+directory-title = members
+directory-heading = members
+directory-search = username
+directory-search-admin = username or email
+directory-newest = newest first
+directory-oldest = oldest first
+directory-filter = search
+directory-joined = joined
+directory-next = next
+directory-empty = No members found.
+
+update-title = update { $email }
+update-heading = update
+update-email = email
+update-username = username
+update-password-change = password change
+
+password-change-title = password change
+password-change-heading = password change
+password-change-password = password
+
+delete-title = delete user
+delete-heading = delete account
+delete-warning = This permanently deletes your account.
+delete-submit = delete
+
+tokens-title = access tokens
+tokens-heading = personal access tokens
+tokens-created = Token { $name } created. Copy it now, it will not be shown again:
+tokens-name = name
+tokens-scopes = scopes
+tokens-expires-after = expires after
+tokens-days = { $count ->
+    [one] { $count } day
+   *[other] { $count } days
+}
+tokens-one-year = 1 year
+tokens-create = create token
+tokens-token = token
+tokens-created-at = created
+tokens-expires-at = expires
+tokens-last-used = last used
+tokens-used-from = { $date } from { $ip }
+tokens-never = never
+tokens-revoked = revoked { $date }
+tokens-revoke = revoke
+tokens-expired = expired
+tokens-choose-scope = Choose at least one scope.
+scope-profile-read = read your profile
+scope-profile-write = change your profile
+
+## Administration
+
+admin-users-title = admin users
+admin-users-heading = users
+admin-id = id
+admin-email = email
+admin-username = username
+admin-verified = verified
+admin-created-at = created
+admin-impersonate = log in as user
+admin-cannot-impersonate-admin = Admins cannot be impersonated.
+
+audit-title = admin audit log
+audit-heading = audit log
+audit-event = event
+audit-actor-id = actor id
+audit-subject-id = subject id
+audit-subject = subject
+audit-filter = filter
+audit-verify-chain = verify chain
+audit-export = export:
+audit-time = time
+audit-actor = actor
+audit-ip = ip
+audit-user-agent = user agent
+audit-request-id = request id
+audit-details = details
+
+audit-verify-title = admin audit verify
+audit-verify-heading = audit chain verification
+audit-verify-events = events checked: { $count }
+audit-verify-checkpoints = checkpoints checked: { $count }
+audit-verify-unchained = unprotected records before the chain: { $count }
+audit-verify-last-event = last event: { $id }
+audit-verify-broken = first broken link at event { $id }: { $reason }
+audit-verify-intact = chain intact
+audit-verify-back = back to audit log
+
+## Errors
+
+error-title = error
+error-not-found-title = not found
+error-home = back to the home page
+error-reference = If you contact support, please quote reference
+error-impersonating = This action is not available while impersonating a user.
+error-not-found = The page you are looking for does not exist.
+error-unavailable = The service is temporarily unavailable. Please try again shortly.
+error-bad-link = This link is invalid or has expired.
+error-email-change-taken = This address already belongs to another account, so your email was not changed.
+error-conflict = This conflicts with an existing record.
+error-invalid-data = The submitted data is not valid.
+error-forbidden = You do not have access to this page.
+error-internal = Something went wrong on our side. Please try again later.
+
+## Validation, keyed by the validator code
+
+validation-invalid = { $field } is invalid
+validation-email = Email is not valid
+validation-login-required = Enter a username or email address
+validation-password-length = Password must be at least { $min } characters
+validation-username-length = Username must be between { $min } and { $max } characters
+validation-username-at = Username cannot contain '@'
+validation-token-name-length = Name must be between { $min } and { $max } characters
+validation-token-expiry-range = Tokens expire after { $min } to { $max } days
+validation-bio-length = The bio can be at most { $max } characters
+
+## Flash messages, shown on the page after a redirect
+
+flash-email-verified = Your email address is verified. You can sign in now.
+flash-email-changed = Your email address has been changed. Sign in with the new address.
+flash-verify-first = Please verify your email address first. We can send you a new link.
+flash-password-reset = Your password has been changed. Sign in with the new password.
+flash-password-changed = Your password has been changed.
+flash-logged-out = You have been signed out.
+flash-account-deleted = Your account has been deleted.
+flash-token-revoked = The token has been revoked.
+flash-public-profile-saved = Your public profile has been saved.
+flash-impersonation-stopped = Impersonation ended. Log in to continue as yourself.
@@ -0,0 +1,265 @@
+# Shown in the language switcher, in the language itself.
+language-name = Русский
+
+## Layout
+
+site-name = Пример на Rust
+base-placeholder = Используйте этот документ, чтобы быстро начать новый проект.
+page-load-time = Время загрузки страницы
+page-load-calculating = вычисляется...
+close = Закрыть
+impersonation-banner = Вы ({ $admin }) действуете от имени { $user } ({ $email }).
+impersonation-stop = вернуться к своей учётной записи
+
+nav-toggle = Показать меню
+nav-user = Профиль
+nav-admin = Администрирование
+nav-audit = Аудит
+nav-directory = Участники
+nav-auth = Вход
+nav-login = войти
+nav-logout = выйти
+nav-signup = регистрация
+nav-email-verify-resend = повторить письмо для подтверждения
+nav-reset-password = сбросить пароль
+
+footer-language = Язык
+footer-features = Возможности
+footer-cool-stuff = Полезное
+footer-random-feature = Случайная функция
+footer-team-feature = Для команд
+footer-developers = Для разработчиков
+footer-another-one = Ещё одна
+footer-last-time = Последняя
+footer-resources = Ресурсы
+footer-resource = Ресурс
+footer-resource-name = Название ресурса
+footer-another-resource = Другой ресурс
+footer-final-resource = Последний ресурс
+footer-about = О нас
+footer-team = Команда
+footer-locations = Офисы
+footer-privacy = Конфиденциальность
+footer-terms = Условия
+
+## Home
+
+index-title = главная
+index-heading = Тарифы
+index-lead = Быстро соберите этот пример на Bootstrap. Он построен на стандартных компонентах и утилитах Bootstrap почти без доработок.
+index-per-month = /мес
+index-plan-free = Бесплатный
+index-plan-pro = Профессиональный
+index-plan-enterprise = Корпоративный
+index-users-included = { $count ->
+    [one] { $count } пользователь
+    [few] { $count } пользователя
+   *[many] { $count } пользователей
+}
+index-storage = { $size } ГБ хранилища
+index-email-support = Поддержка по почте
+index-priority-support = Приоритетная поддержка по почте
+index-phone-support = Поддержка по телефону и почте
+index-help-center = Доступ к справочному центру
+index-sign-up-free = Зарегистрироваться бесплатно
+index-get-started = Начать
+index-contact-us = Связаться с нами
+
+## Forms
+
+form-email = Адрес электронной почты
+form-password = Пароль
+form-username = Имя пользователя
+form-login = Имя пользователя или адрес электронной почты
+form-submit = сохранить
+form-send-email = отправить письмо
+
+## Accounts
+
+login-title = Вход
+login-heading = Войдите, пожалуйста
+login-remember-me = Запомнить меня
+login-submit = Войти
+login-reset-password = сбросить пароль
+login-invalid-credentials = Неверное имя пользователя, адрес электронной почты или пароль.
+login-account-disabled = Эта учётная запись отключена.
+
+logout-title = выход
+logout-submit = выйти
+
+signup-title = регистрация
+signup-heading = Зарегистрируйтесь, пожалуйста
+signup-submit = Зарегистрироваться
+signup-login = войти
+signup-username-taken = Это имя пользователя уже занято.
+
+verify-resend-title = повторное подтверждение
+verify-resend-heading = Подтверждение почты
+verify-sent-title = проверьте почту
+verify-sent-heading = Проверьте почту
+verify-sent-body = Если с этим адресом можно использовать учётную запись, мы отправили на него письмо с дальнейшими инструкциями.
+
+reset-password-title = сброс пароля
+reset-password-heading = Укажите имя пользователя или адрес электронной почты
+reset-password-confirm-heading = Придумайте новый пароль
+reset-password-confirm-submit = изменить
+
+## Profile
+
+profile-user = пользователь: { $email }
+profile-title = пользователь { $username }
+profile-id = id: { $id }
+profile-email = почта: { $email }
+profile-username = имя пользователя: { $username }
+profile-created-at = создан: { $date }
+profile-updated-at = изменён: { $date }
+profile-edit = Редактировать профиль
+profile-tokens = Токены доступа
+profile-delete = Удалить учётную запись
+profile-public = Публичный профиль
+
+public-title = { $username }
+public-joined = с нами с { $date }
+public-no-bio = Рассказа о себе пока нет.
+public-edit = изменить публичный профиль
+
+public-settings-title = публичный профиль
+public-settings-heading = публичный профиль
+public-settings-bio = о себе
+public-settings-visibility = кто видит вашу страницу
+public-settings-view = открыть вашу страницу
+
+visibility-public = все
+visibility-members = вошедшие пользователи
+visibility-hidden = только вы
+
+directory-title = участники
+directory-heading = участники
+directory-search = имя пользователя
+directory-search-admin = имя пользователя или почта
+directory-newest = сначала новые
+directory-oldest = сначала старые
+directory-filter = искать
+directory-joined = с нами с
+directory-next = дальше
+directory-empty = Никого не нашлось.
+
+update-title = изменение { $email }
+update-heading = изменение профиля
+update-email = почта
+update-username = имя пользователя
+update-password-change = сменить пароль
+
+password-change-title = смена пароля
+password-change-heading = смена пароля
+password-change-password = пароль
+
+delete-title = удаление пользователя
+delete-heading = удаление учётной записи
+delete-warning = Учётная запись будет удалена безвозвратно.
+delete-submit = удалить
+
+tokens-title = токены доступа
+tokens-heading = персональные токены доступа
+tokens-created = Токен { $name } создан. Скопируйте его сейчас, больше он показан не будет:
+tokens-name = название
+tokens-scopes = права
+tokens-expires-after = срок действия
+tokens-days = { $count ->
+    [one] { $count } день
+    [few] { $count } дня
+   *[many] { $count } дней
+}
+tokens-one-year = 1 год
+tokens-create = создать токен
+tokens-token = токен
+tokens-created-at = создан
+tokens-expires-at = истекает
+tokens-last-used = последнее использование
+tokens-used-from = { $date } с { $ip }
+tokens-never = никогда
+tokens-revoked = отозван { $date }
+tokens-revoke = отозвать
+tokens-expired = истёк
+tokens-choose-scope = Выберите хотя бы одно право.
+scope-profile-read = чтение профиля
+scope-profile-write = изменение профиля
+
+## Administration
+
+admin-users-title = пользователи
+admin-users-heading = пользователи
+admin-id = id
+admin-email = почта
+admin-username = имя пользователя
+admin-verified = подтверждён
+admin-created-at = создан
+admin-impersonate = войти как пользователь
+admin-cannot-impersonate-admin = Нельзя действовать от имени администратора.
+
+audit-title = журнал аудита
+audit-heading = журнал аудита
+audit-event = событие
+audit-actor-id = id инициатора
+audit-subject-id = id объекта
+audit-subject = объект
+audit-filter = отфильтровать
+audit-verify-chain = проверить цепочку
+audit-export = выгрузить:
+audit-time = время
+audit-actor = инициатор
+audit-ip = ip
+audit-user-agent = user agent
+audit-request-id = id запроса
+audit-details = подробности
+
+audit-verify-title = проверка журнала аудита
+audit-verify-heading = проверка цепочки аудита
+audit-verify-events = проверено событий: { $count }
+audit-verify-checkpoints = проверено контрольных точек: { $count }
+audit-verify-unchained = незащищённых записей до начала цепочки: { $count }
+audit-verify-last-event = последнее событие: { $id }
+audit-verify-broken = первое нарушение цепочки на событии { $id }: { $reason }
+audit-verify-intact = цепочка не нарушена
+audit-verify-back = назад к журналу аудита
+
+## Errors
+
+error-title = ошибка
+error-not-found-title = не найдено
+error-home = на главную страницу
+error-reference = Обращаясь в поддержку, укажите номер
+error-impersonating = Это действие недоступно, пока вы действуете от имени другого пользователя.
+error-not-found = Такой страницы не существует.
+error-unavailable = Сервис временно недоступен. Попробуйте ещё раз чуть позже.
+error-bad-link = Ссылка недействительна или устарела.
+error-email-change-taken = Этот адрес уже принадлежит другой учётной записи, поэтому адрес не изменён.
+error-conflict = Такая запись уже существует.
+error-invalid-data = Отправленные данные некорректны.
+error-forbidden = У вас нет доступа к этой странице.
+error-internal = Что-то пошло не так. Попробуйте ещё раз позже.
+
+## Validation, keyed by the validator code
+
+validation-invalid = Поле { $field } заполнено неверно
+validation-email = Некорректный адрес электронной почты
+validation-login-required = Укажите имя пользователя или адрес электронной почты
+validation-password-length = Пароль должен содержать не менее { $min } символов
+validation-username-length = Имя пользователя должно содержать от { $min } до { $max } символов
+validation-username-at = Имя пользователя не может содержать '@'
+validation-token-name-length = Название должно содержать от { $min } до { $max } символов
+validation-token-expiry-range = Срок действия токена — от { $min } до { $max } дней
+validation-bio-length = Рассказ о себе — не больше { $max } символов
+
+## Flash messages, shown on the page after a redirect
+
+flash-email-verified = Адрес электронной почты подтверждён. Теперь можно войти.
+flash-email-changed = Адрес электронной почты изменён. Войдите, используя новый адрес.
+flash-verify-first = Сначала подтвердите адрес электронной почты. Мы можем отправить новую ссылку.
+flash-password-reset = Пароль изменён. Войдите с новым паролем.
+flash-password-changed = Пароль изменён.
+flash-logged-out = Вы вышли из учётной записи.
+flash-account-deleted = Учётная запись удалена.
+flash-token-revoked = Токен отозван.
+flash-public-profile-saved = Публичный профиль сохранён.
+flash-impersonation-stopped = Режим действия от имени пользователя завершён. Войдите, чтобы продолжить под своей учётной записью.
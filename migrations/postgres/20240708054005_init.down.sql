@@ -1,5 +1,5 @@
 -- Add down migration script here
 
 -- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS sessions;
 DROP TABLE IF EXISTS users;
-DROP TABLE IF EXISTS sessions
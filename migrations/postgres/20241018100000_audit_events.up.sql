@@ -0,0 +1,28 @@
+-- Add up migration script here
+
+CREATE TABLE audit_events (
+    id          BIGSERIAL    PRIMARY KEY,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    event       TEXT         NOT NULL,
+    actor_id    INTEGER,
+    subject_id  INTEGER,
+    subject     TEXT,
+    ip          TEXT,
+    user_agent  TEXT,
+    request_id  TEXT,
+    details     TEXT
+);
+
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
+CREATE INDEX audit_events_event_idx ON audit_events (event);
+
+-- the audit log is append-only
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+    BEFORE UPDATE OR DELETE OR TRUNCATE ON audit_events
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
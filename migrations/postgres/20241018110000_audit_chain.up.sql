@@ -0,0 +1,24 @@
+-- Add up migration script here
+
+-- rows written before chaining was introduced keep NULL hashes and are
+-- skipped by the verifier
+ALTER TABLE audit_events ADD COLUMN prev_hash TEXT;
+ALTER TABLE audit_events ADD COLUMN hash TEXT;
+
+CREATE TABLE audit_checkpoints (
+    id          BIGSERIAL    PRIMARY KEY,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    event_id    BIGINT       NOT NULL REFERENCES audit_events(id),
+    hash        TEXT         NOT NULL,
+    signature   TEXT         NOT NULL
+);
+
+CREATE OR REPLACE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_checkpoints_append_only
+    BEFORE UPDATE OR DELETE OR TRUNCATE ON audit_checkpoints
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
@@ -0,0 +1,18 @@
+-- Add up migration script here
+
+-- Personal access tokens; only a lookup prefix and a hash of the secret are stored
+CREATE TABLE api_tokens (
+    id           SERIAL       PRIMARY KEY,
+    user_id      INTEGER      NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name         TEXT         NOT NULL,
+    prefix       TEXT         NOT NULL UNIQUE,
+    token_hash   TEXT         NOT NULL,
+    scopes       TEXT[]       NOT NULL,
+    created_at   TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    expires_at   TIMESTAMPTZ  NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    last_used_ip TEXT,
+    revoked_at   TIMESTAMPTZ
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens (user_id);
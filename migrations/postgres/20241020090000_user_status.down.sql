@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE users DROP COLUMN IF EXISTS sessions_revoked_at;
+ALTER TABLE users DROP COLUMN IF EXISTS disabled_at;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- disabled accounts cannot log in; session tokens issued before
+-- sessions_revoked_at are no longer accepted
+ALTER TABLE users ADD COLUMN disabled_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN sessions_revoked_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- language picked by the user; NULL follows the browser
+ALTER TABLE users ADD COLUMN locale TEXT;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- shown on /u/{username}; visibility is one of public, members, hidden
+ALTER TABLE users ADD COLUMN bio TEXT;
+ALTER TABLE users ADD COLUMN visibility TEXT NOT NULL DEFAULT 'members'
+    CHECK (visibility IN ('public', 'members', 'hidden'));
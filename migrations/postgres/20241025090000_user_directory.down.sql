@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+-- pg_trgm stays installed, other schemas may use it
+DROP INDEX IF EXISTS users_created_at_id_idx;
+DROP INDEX IF EXISTS users_email_trgm_idx;
+DROP INDEX IF EXISTS users_username_trgm_idx;
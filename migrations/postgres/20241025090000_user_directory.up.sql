@@ -0,0 +1,8 @@
+-- Add up migration script here
+
+-- member directory: trigram search over usernames and emails, and keyset
+-- pagination by join date
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX users_username_trgm_idx ON users USING gin (username gin_trgm_ops);
+CREATE INDEX users_email_trgm_idx ON users USING gin (email gin_trgm_ops);
+CREATE INDEX users_created_at_id_idx ON users (created_at, id);
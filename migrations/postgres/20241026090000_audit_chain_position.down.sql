@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS audit_events_chain_position_key;
+ALTER TABLE audit_events DROP COLUMN IF EXISTS chain_position;
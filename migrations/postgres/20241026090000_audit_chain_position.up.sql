@@ -0,0 +1,7 @@
+-- Add up migration script here
+
+-- 1-based position among chained records; checkpoints are due at every
+-- CHECKPOINT_INTERVAL positions, so gaps in the id sequence cannot skip
+-- them. NULL on records written before positions were stored.
+ALTER TABLE audit_events ADD COLUMN chain_position BIGINT;
+CREATE UNIQUE INDEX audit_events_chain_position_key ON audit_events (chain_position);
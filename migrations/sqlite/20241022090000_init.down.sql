@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS audit_checkpoints;
+DROP TABLE IF EXISTS audit_events;
+DROP TABLE IF EXISTS api_tokens;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,81 @@
+-- The schema of the Postgres migrations up to 20241021090000_user_locale.
+-- Timestamps are RFC 3339 text in UTC and token scopes a JSON array.
+
+CREATE TABLE users (
+    id                  INTEGER  PRIMARY KEY AUTOINCREMENT,
+    email               TEXT     NOT NULL UNIQUE,
+    username            TEXT     NOT NULL UNIQUE,
+    password            TEXT     NOT NULL,
+    is_verify           BOOLEAN  NOT NULL DEFAULT false,
+    img                 TEXT,
+    created_at          TEXT     NOT NULL,
+    updated_at          TEXT,
+    is_admin            BOOLEAN  NOT NULL DEFAULT false,
+    disabled_at         TEXT,
+    sessions_revoked_at TEXT,
+    locale              TEXT
+);
+
+-- Personal access tokens; only a lookup prefix and a hash of the secret are stored
+CREATE TABLE api_tokens (
+    id           INTEGER  PRIMARY KEY AUTOINCREMENT,
+    user_id      INTEGER  NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name         TEXT     NOT NULL,
+    prefix       TEXT     NOT NULL UNIQUE,
+    token_hash   TEXT     NOT NULL,
+    scopes       TEXT     NOT NULL,
+    created_at   TEXT     NOT NULL,
+    expires_at   TEXT     NOT NULL,
+    last_used_at TEXT,
+    last_used_ip TEXT,
+    revoked_at   TEXT
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens (user_id);
+
+CREATE TABLE audit_events (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    created_at  TEXT     NOT NULL,
+    event       TEXT     NOT NULL,
+    actor_id    INTEGER,
+    subject_id  INTEGER,
+    subject     TEXT,
+    ip          TEXT,
+    user_agent  TEXT,
+    request_id  TEXT,
+    details     TEXT,
+    prev_hash   TEXT,
+    hash        TEXT
+);
+
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
+CREATE INDEX audit_events_event_idx ON audit_events (event);
+
+CREATE TABLE audit_checkpoints (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    event_id    INTEGER  NOT NULL REFERENCES audit_events(id),
+    hash        TEXT     NOT NULL,
+    signature   TEXT     NOT NULL
+);
+
+-- the audit log is append-only
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+
+CREATE TRIGGER audit_checkpoints_no_update BEFORE UPDATE ON audit_checkpoints
+BEGIN
+    SELECT RAISE(ABORT, 'audit_checkpoints is append-only');
+END;
+
+CREATE TRIGGER audit_checkpoints_no_delete BEFORE DELETE ON audit_checkpoints
+BEGIN
+    SELECT RAISE(ABORT, 'audit_checkpoints is append-only');
+END;
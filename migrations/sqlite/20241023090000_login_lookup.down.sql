@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS users_lower_username_idx;
+DROP INDEX IF EXISTS users_lower_email_idx;
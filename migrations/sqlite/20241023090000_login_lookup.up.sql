@@ -0,0 +1,5 @@
+-- Add up migration script here
+
+-- login accepts a username or an email in any case
+CREATE INDEX users_lower_email_idx ON users (lower(email));
+CREATE INDEX users_lower_username_idx ON users (lower(username));
@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE users DROP COLUMN visibility;
+ALTER TABLE users DROP COLUMN bio;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- keyset pagination of the member directory by join date
+CREATE INDEX users_created_at_id_idx ON users (created_at, id);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS users_username_lower_key;
+DROP INDEX IF EXISTS users_email_lower_key;
+CREATE INDEX users_lower_email_idx ON users (lower(email));
+CREATE INDEX users_lower_username_idx ON users (lower(username));
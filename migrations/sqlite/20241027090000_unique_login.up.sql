@@ -0,0 +1,7 @@
+-- Add up migration script here
+
+-- a login has to name exactly one user, whatever its case
+DROP INDEX IF EXISTS users_lower_email_idx;
+DROP INDEX IF EXISTS users_lower_username_idx;
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
+CREATE UNIQUE INDEX users_username_lower_key ON users (lower(username));
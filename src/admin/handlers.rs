@@ -0,0 +1,83 @@
+use axum::{
+    Extension,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::common::{build_redirect_with_cookie, html_err, page_context, render, AuthenticatedUser, RequestMeta, Templates};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::utils::flash::Flash;
+use crate::utils::jwt::encode_impersonation_jwt;
+
+async fn users_page(
+    state: &AppState,
+    templates: &Templates,
+    user: &AuthenticatedUser,
+    message: Option<String>,
+) -> Result<Response, AppError> {
+    let mut context = page_context(user);
+
+    let users = state.users.list().await?;
+    context.insert("users", &users);
+    match message {
+        Some(message) => html_err(templates, "admin-users", &mut context, message).await,
+        None => Ok(render(templates, "admin-users", &context)?.into_response()),
+    }
+}
+
+pub async fn users(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+) -> Result<Response, AppError> {
+    users_page(&state, &templates, &user, None).await
+}
+
+pub async fn post_impersonate(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    Path(id): Path<i32>,
+    admin: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<Response, AppError> {
+    let target = state.users.find_by_id(id).await?;
+
+    if target.id == admin.id || target.is_admin {
+        return users_page(&state, &templates, &admin, Some(templates.t("admin-cannot-impersonate-admin"))).await;
+    }
+
+    // impersonation sessions are deliberately short-lived
+    let token = encode_impersonation_jwt(target.id, target.email.clone(), admin.id, 1)
+        .await
+        .map_err(AppError::Jwt)?;
+
+    record(state.audit.as_ref(), NewAuditEvent::new("impersonation.start", &meta)
+        .actor(admin.id)
+        .subject_id(target.id)
+        .subject(&target.email)).await;
+    Ok(build_redirect_with_cookie(&token, "3600".to_string(), "/account/detail"))
+}
+
+pub async fn post_stop_impersonate(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<Response, AppError> {
+    let impersonator = match &user.impersonator {
+        Some(impersonator) => impersonator.clone(),
+        None => return Err(AppError::Forbidden),
+    };
+
+    // the admin logs in again rather than getting a session minted from
+    // this cookie, which would outlive a revocation of their own sessions
+    record(state.audit.as_ref(), NewAuditEvent::new("impersonation.stop", &meta)
+        .actor(impersonator.id)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    flash.info(templates.t("flash-impersonation-stopped"));
+    Ok(build_redirect_with_cookie("", "0".to_string(), "/account/login"))
+}
@@ -0,0 +1,66 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::api::handlers;
+use crate::api::models::{
+    Accepted, ApiErrorBody, ApiErrorResponse, ResetConfirmRequest, TokenRequest, TokenResponse,
+};
+use crate::auth::models::{FormLogin, ListUser};
+use crate::profile::models::{FormPasswordReset, FormSingUpUser, FormUpdateUser, FormVerifyEmail};
+
+/// OpenAPI document of `/api/v1`, served at `/api/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "axum-sqlx-tera-jwt-auth API", description = "JSON API for accounts and sessions."),
+    paths(
+        handlers::signup,
+        handlers::login,
+        handlers::logout,
+        handlers::verify_email,
+        handlers::verify_email_resend,
+        handlers::password_reset,
+        handlers::password_reset_confirm,
+        handlers::get_profile,
+        handlers::update_profile,
+    ),
+    components(schemas(
+        Accepted,
+        ApiErrorBody,
+        ApiErrorResponse,
+        FormLogin,
+        FormPasswordReset,
+        FormSingUpUser,
+        FormUpdateUser,
+        FormVerifyEmail,
+        ListUser,
+        ResetConfirmRequest,
+        TokenRequest,
+        TokenResponse,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Signup, login and account recovery"),
+        (name = "profile", description = "The authenticated user"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT or personal access token")
+                    .description(Some(
+                        "A session token from /api/v1/login, or a `pat_` token created on the account page.",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
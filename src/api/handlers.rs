@@ -0,0 +1,422 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+
+use crate::api::models::{
+    require_scope, validate, Accepted, ApiError, ApiJson, ResetConfirmRequest, TokenRequest, TokenResponse,
+};
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::auth::models::{Claims, FormLogin, ListUser};
+use crate::common::{build_redirect_with_cookie, AuthenticatedUser, RequestMeta};
+use crate::error::AppError;
+use crate::metrics;
+use crate::profile::models::{
+    FormPasswordChange, FormPasswordReset, FormSingUpUser, FormUpdateUser, FormVerifyEmail, NewUser, PasswordChange,
+    UpdateUser, UpdateUserEmailVerify,
+};
+use crate::state::AppState;
+use crate::utils::jwt::{
+    ar_dummy_verify, ar_hash_password, ar_verify_password, decode_token, encode_email_change_jwt, encode_jwt,
+    encode_session, session_hours,
+};
+use crate::utils::mail;
+
+const USERNAME_TAKEN: &str = "This username is already taken.";
+const CHECK_EMAIL: Accepted = Accepted {
+    message: "If an account can be used with this address, we have sent it a message with the next steps.",
+};
+
+async fn token_claims(token: String, purpose: &str) -> Result<Claims, ApiError> {
+    decode_token(token)
+        .await
+        .ok()
+        .flatten()
+        .filter(|claims| claims.purpose == purpose)
+        .ok_or(ApiError::InvalidToken)
+}
+
+fn accepted() -> Response {
+    (StatusCode::ACCEPTED, Json(CHECK_EMAIL)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/signup",
+    tag = "auth",
+    request_body = FormSingUpUser,
+    responses(
+        (status = 202, description = "Verification mail sent if the address can be used", body = Accepted),
+        (status = 400, description = "Malformed body", body = ApiErrorResponse),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn signup(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(form): ApiJson<FormSingUpUser>,
+) -> Result<Response, ApiError> {
+    validate(&form)?;
+
+    let hashed_password = ar_hash_password(&form.password)?;
+
+    // advisory only: the unique constraints below are what decide
+    if state.users.username_exists(&form.username).await? {
+        return Err(ApiError::Field("username", USERNAME_TAKEN));
+    }
+
+    let new_user = NewUser {
+        email: form.email,
+        username: form.username,
+        password: hashed_password,
+        is_verify: false,
+        created_at: Utc::now(),
+    };
+
+    if let Err(e) = state.users.create(new_user.clone()).await {
+        return match e.user_field() {
+            Some("email") => {
+                record(state.audit.as_ref(), NewAuditEvent::new("signup.duplicate", &meta)
+                    .subject(&new_user.email)
+                    .details("email exists")).await;
+                mail::send(mail::account_exists(&new_user.email));
+                Ok(accepted())
+            }
+            Some("username") => Err(ApiError::Field("username", USERNAME_TAKEN)),
+            _ => Err(e.into()),
+        };
+    }
+    record(state.audit.as_ref(), NewAuditEvent::new("signup", &meta).subject(&new_user.email)).await;
+
+    let token = encode_jwt(new_user.email.clone(), "email-verify".to_string(), 1)
+        .await
+        .map_err(AppError::Jwt)?;
+    mail::send(mail::verify_email(&new_user.email, &token));
+    Ok(accepted())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/login",
+    tag = "auth",
+    request_body = FormLogin,
+    responses(
+        (status = 200, description = "Session token", body = TokenResponse),
+        (status = 401, description = "Invalid username, email or password", body = ApiErrorResponse),
+        (status = 403, description = "Email address is not verified, or the account is disabled", body = ApiErrorResponse),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(form): ApiJson<FormLogin>,
+) -> Result<Json<TokenResponse>, ApiError> {
+    validate(&form)?;
+
+    // unknown logins and wrong passwords get the same answer and cost
+    let user = match state.users.find_by_login(&form.login).await {
+        Ok(user) => user,
+        Err(_) => {
+            ar_dummy_verify(&form.password);
+            metrics::login_attempt("api", false);
+            record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+                .subject(&form.login)
+                .details("unknown user")).await;
+            return Err(ApiError::InvalidCredentials);
+        }
+    };
+
+    if !matches!(ar_verify_password(&form.password, &user.password), Ok(true)) {
+        metrics::login_attempt("api", false);
+        record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details("invalid password")).await;
+        return Err(ApiError::InvalidCredentials);
+    }
+    if user.is_disabled() {
+        metrics::login_attempt("api", false);
+        record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details("account disabled")).await;
+        return Err(ApiError::Disabled);
+    }
+    if !user.is_verify {
+        metrics::login_attempt("api", false);
+        return Err(ApiError::Unverified);
+    }
+
+    let token = encode_session(user.email.clone())
+        .await
+        .map_err(AppError::Jwt)?;
+    metrics::login_attempt("api", true);
+    record(state.audit.as_ref(), NewAuditEvent::new("login.success", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    Ok(Json(TokenResponse {
+        token,
+        token_type: "Bearer",
+        expires_in: session_hours() * 3600,
+    }))
+}
+
+/// Session tokens are stateless: the client discards its token. The cookie
+/// is cleared too in case the API is called from a browser session.
+#[utoipa::path(
+    post,
+    path = "/api/v1/logout",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Logged out"),
+        (status = 401, description = "Authentication required", body = ApiErrorResponse),
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Response {
+    record(state.audit.as_ref(), NewAuditEvent::new("logout", &meta)
+        .actor(user.impersonator.as_ref().map_or(user.id, |impersonator| impersonator.id))
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    let mut response = build_redirect_with_cookie("", "0".to_string(), "/");
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    response.headers_mut().remove("Location");
+    response
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/email-verify",
+    tag = "auth",
+    request_body = TokenRequest,
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 400, description = "Invalid or expired token", body = ApiErrorResponse),
+    )
+)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(body): ApiJson<TokenRequest>,
+) -> Result<StatusCode, ApiError> {
+    let claims = token_claims(body.token, "email-verify").await?;
+
+    let update = UpdateUserEmailVerify {
+        email: claims.email.clone(),
+        is_verify: true,
+        updated_at: Some(Utc::now()),
+    };
+    state.users.set_verified(update).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("email.verify", &meta).subject(&claims.email)).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/email-verify-resend",
+    tag = "auth",
+    request_body = FormVerifyEmail,
+    responses(
+        (status = 202, description = "Verification mail sent if the account is unverified", body = Accepted),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn verify_email_resend(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(form): ApiJson<FormVerifyEmail>,
+) -> Result<Response, ApiError> {
+    validate(&form)?;
+
+    // the response is the same whether or not the account exists
+    let user = match state.users.find_by_email(&form.email).await {
+        Ok(user) => user,
+        Err(_) => {
+            record(state.audit.as_ref(), NewAuditEvent::new("email.verify_resend", &meta)
+                .subject(&form.email)
+                .details("unknown email")).await;
+            return Ok(accepted());
+        }
+    };
+
+    if !user.is_verify {
+        let token = encode_jwt(user.email.clone(), "email-verify".to_string(), 1)
+            .await
+            .map_err(AppError::Jwt)?;
+        mail::send(mail::verify_email(&user.email, &token));
+    }
+    record(state.audit.as_ref(), NewAuditEvent::new("email.verify_resend", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    Ok(accepted())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/reset-password",
+    tag = "auth",
+    request_body = FormPasswordReset,
+    responses(
+        (status = 202, description = "Reset mail sent if the account exists", body = Accepted),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn password_reset(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(form): ApiJson<FormPasswordReset>,
+) -> Result<Response, ApiError> {
+    validate(&form)?;
+
+    // the response is the same whether or not the account exists
+    let user = match state.users.find_by_login(&form.login).await {
+        Ok(user) => user,
+        Err(_) => {
+            record(state.audit.as_ref(), NewAuditEvent::new("password_reset.request", &meta)
+                .subject(&form.login)
+                .details("unknown user")).await;
+            return Ok(accepted());
+        }
+    };
+
+    let token = encode_jwt(user.email.clone(), "reset-password".to_string(), 1)
+        .await
+        .map_err(AppError::Jwt)?;
+    record(state.audit.as_ref(), NewAuditEvent::new("password_reset.request", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    mail::send(mail::reset_password(&user.email, &token));
+    Ok(accepted())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/reset-password-confirm",
+    tag = "auth",
+    request_body = ResetConfirmRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid or expired token", body = ApiErrorResponse),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn password_reset_confirm(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    ApiJson(body): ApiJson<ResetConfirmRequest>,
+) -> Result<StatusCode, ApiError> {
+    let form = FormPasswordChange { password: body.password };
+    validate(&form)?;
+
+    let claims = token_claims(body.token, "reset-password").await?;
+    let user = state.users.find_by_email(&claims.email).await?;
+
+    let password_change = PasswordChange {
+        email: user.email.clone(),
+        password: ar_hash_password(&form.password)?,
+        updated_at: Some(Utc::now()),
+    };
+    state.users.update_password(password_change).await?;
+    // whoever reset the password may be locking someone else out
+    state.sessions.revoke_all(&user.email).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("password_reset.complete", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile",
+    tag = "profile",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The authenticated user", body = ListUser),
+        (status = 401, description = "Authentication required", body = ApiErrorResponse),
+        (status = 403, description = "Token lacks the profile:read scope", body = ApiErrorResponse),
+    )
+)]
+pub async fn get_profile(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<Json<ListUser>, ApiError> {
+    require_scope(&user, "profile:read")?;
+    Ok(Json(ListUser::from(state.users.find_by_id(user.id).await?)))
+}
+
+/// A new email address is mailed a confirmation link and only replaces the
+/// current one once that is followed, which ends the sessions bound to the
+/// old address. The response is the same whether or not another account
+/// has the address; only that account's owner is told.
+#[utoipa::path(
+    put,
+    path = "/api/v1/profile",
+    tag = "profile",
+    security(("bearer" = [])),
+    request_body = FormUpdateUser,
+    responses(
+        (status = 200, description = "The updated user", body = ListUser),
+        (status = 401, description = "Authentication required", body = ApiErrorResponse),
+        (status = 403, description = "Token lacks the profile:write scope", body = ApiErrorResponse),
+        (status = 422, description = "Validation failed", body = ApiErrorResponse),
+    )
+)]
+pub async fn update_profile(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+    ApiJson(form): ApiJson<FormUpdateUser>,
+) -> Result<Json<ListUser>, ApiError> {
+    require_scope(&user, "profile:write")?;
+    validate(&form)?;
+
+    let update = UpdateUser {
+        email: user.email.clone(),
+        username: form.username.clone(),
+        updated_at: Some(Utc::now()),
+    };
+    if let Err(e) = state.users.update_profile(user.id, update).await {
+        return Err(match e.user_field() {
+            Some("username") => ApiError::Field("username", USERNAME_TAKEN),
+            _ => e.into(),
+        });
+    }
+
+    let actor = user.impersonator.as_ref().map_or(user.id, |impersonator| impersonator.id);
+    if form.email != user.email {
+        let event = NewAuditEvent::new("email.change_request", &meta)
+            .actor(actor)
+            .subject_id(user.id)
+            .subject(&form.email);
+        match state.users.find_by_email(&form.email).await {
+            Ok(_) => {
+                record(state.audit.as_ref(), event.details("email exists")).await;
+                mail::send(mail::email_change_taken(&form.email));
+            }
+            Err(_) => {
+                let token = encode_email_change_jwt(user.id, form.email.clone(), 1)
+                    .await
+                    .map_err(AppError::Jwt)?;
+                record(state.audit.as_ref(), event).await;
+                mail::send(mail::confirm_email_change(&form.email, &token));
+            }
+        }
+    }
+
+    let updated = ListUser::from(state.users.find_by_id(user.id).await?);
+    record(state.audit.as_ref(), NewAuditEvent::new("profile.update", &meta)
+        .actor(actor)
+        .subject_id(user.id)
+        .subject(&updated.email)).await;
+    Ok(Json(updated))
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use axum_macros::FromRequest;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationErrors};
+
+use crate::common::AuthenticatedUser;
+use crate::error::AppError;
+use crate::i18n::Locale;
+
+/// `Json` extractor whose rejections use the API error body.
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiErrorBody {
+    pub code: u16,
+    pub message: String,
+    /// Validation messages keyed by field name.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub fields: HashMap<String, Vec<String>>,
+    /// Reference to quote to support, present on server errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// Envelope of every API error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiErrorResponse {
+    pub error: ApiErrorBody,
+}
+
+/// Errors returned by the JSON API as `{"error": {...}}` bodies.
+#[derive(Debug)]
+pub enum ApiError {
+    Validation(ValidationErrors),
+    Field(&'static str, &'static str),
+    InvalidCredentials,
+    Unverified,
+    Disabled,
+    Unauthorized,
+    InvalidToken,
+    InsufficientScope(&'static str),
+    BadRequest(String),
+    App(AppError),
+}
+
+impl<E: Into<AppError>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        ApiError::App(err.into())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl ApiError {
+    fn body(status: StatusCode, message: &str, fields: HashMap<String, Vec<String>>) -> Response {
+        let body = ApiErrorBody {
+            code: status.as_u16(),
+            message: message.to_string(),
+            fields,
+            correlation_id: None,
+        };
+        (status, Json(ApiErrorResponse { error: body })).into_response()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Validation(errors) => {
+                // API clients get the default language
+                let fields = Locale::default().validation_messages(&errors).into_iter().collect();
+                Self::body(StatusCode::UNPROCESSABLE_ENTITY, "Validation failed", fields)
+            }
+            ApiError::Field(field, message) => Self::body(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Validation failed",
+                HashMap::from([(field.to_string(), vec![message.to_string()])]),
+            ),
+            ApiError::InvalidCredentials => {
+                Self::body(StatusCode::UNAUTHORIZED, "Invalid username, email or password.", HashMap::new())
+            }
+            ApiError::Unverified => {
+                Self::body(StatusCode::FORBIDDEN, "Email address is not verified.", HashMap::new())
+            }
+            ApiError::Disabled => {
+                Self::body(StatusCode::FORBIDDEN, "This account is disabled.", HashMap::new())
+            }
+            ApiError::Unauthorized => {
+                Self::body(StatusCode::UNAUTHORIZED, "Authentication required.", HashMap::new())
+            }
+            ApiError::InvalidToken => {
+                Self::body(StatusCode::BAD_REQUEST, "This token is invalid or has expired.", HashMap::new())
+            }
+            ApiError::InsufficientScope(scope) => Self::body(
+                StatusCode::FORBIDDEN,
+                &format!("This token does not have the {} scope.", scope),
+                HashMap::new(),
+            ),
+            ApiError::BadRequest(message) => Self::body(StatusCode::BAD_REQUEST, &message, HashMap::new()),
+            // rendered as JSON by `render_errors` for /api paths
+            ApiError::App(err) => err.into_response(),
+        }
+    }
+}
+
+/// Personal access tokens only reach the endpoints their scopes allow.
+pub fn require_scope(user: &AuthenticatedUser, scope: &'static str) -> Result<(), ApiError> {
+    if user.has_scope(scope) {
+        Ok(())
+    } else {
+        Err(ApiError::InsufficientScope(scope))
+    }
+}
+
+/// Run the `validator` rules of a request body.
+pub fn validate<T: Validate>(form: &T) -> Result<(), ApiError> {
+    form.validate().map_err(ApiError::Validation)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Accepted {
+    pub message: &'static str,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ResetConfirmRequest {
+    pub token: String,
+    #[schema(min_length = 8, format = Password)]
+    pub password: String,
+}
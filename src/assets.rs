@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use axum::body::{Body, Bytes};
+use axum::extract::Path;
+use axum::http::header::{
+    ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY,
+};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+
+/// Where the assets are served.
+pub const ASSETS_PREFIX: &str = "/assets";
+
+/// Fingerprinted URLs change with the content, so they never go stale.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Plain URLs may be cached but must be revalidated with the ETag.
+const REVALIDATE: &str = "public, no-cache";
+
+/// A file from `assets/`, embedded by `build.rs`.
+pub struct Asset {
+    /// Path under `assets/`, e.g. `css/style.css`.
+    pub path: &'static str,
+    /// The path with the content hash, e.g. `css/style.1a2b3c4d5e6f7a8b.css`.
+    pub hashed_path: &'static str,
+    pub content_type: &'static str,
+    pub hash: &'static str,
+    pub body: &'static [u8],
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+}
+
+static ASSETS: &[Asset] = include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// Every asset by both its plain and its hashed path; the flag tells
+/// whether the path was the hashed one.
+static BY_PATH: Lazy<HashMap<&'static str, (&'static Asset, bool)>> = Lazy::new(|| {
+    let mut paths = HashMap::with_capacity(ASSETS.len() * 2);
+    for asset in ASSETS {
+        paths.insert(asset.path, (asset, false));
+        paths.insert(asset.hashed_path, (asset, true));
+    }
+    paths
+});
+
+/// The cache-busting URL of the asset at `path` under `assets/`.
+pub fn url(path: &str) -> Option<String> {
+    let (asset, _) = BY_PATH.get(path.trim_start_matches('/'))?;
+    Some(format!("{}/{}", ASSETS_PREFIX, asset.hashed_path))
+}
+
+/// The Tera `asset_url()` function: `{{ asset_url(path="css/style.css") }}`.
+/// Unknown paths fail the render, so typos show up at once.
+pub fn tera_function() -> impl tera::Function {
+    AssetUrl
+}
+
+/// URLs come from the built asset table, so templates need not escape them.
+struct AssetUrl;
+
+impl tera::Function for AssetUrl {
+    fn call(&self, args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+        let path = match args.get("path").and_then(|path| path.as_str()) {
+            Some(path) => path,
+            None => return Err(tera::Error::msg("asset_url() needs a `path` argument")),
+        };
+        match url(path) {
+            Some(url) => Ok(tera::Value::String(url)),
+            None => Err(tera::Error::msg(format!("no asset {} under assets/", path))),
+        }
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+/// Whether the client lists `coding` in `Accept-Encoding` without `q=0`.
+fn accepts(headers: &HeaderMap, coding: &str) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            name.eq_ignore_ascii_case(coding) && !refused
+        })
+}
+
+fn representation(asset: &Asset, headers: &HeaderMap) -> (Encoding, &'static [u8]) {
+    match (asset.brotli, asset.gzip) {
+        (Some(body), _) if accepts(headers, "br") => (Encoding::Brotli, body),
+        (_, Some(body)) if accepts(headers, "gzip") => (Encoding::Gzip, body),
+        _ => (Encoding::Identity, asset.body),
+    }
+}
+
+/// Whether `If-None-Match` already names `etag`.
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+/// Serve an embedded asset in the best encoding the client accepts.
+pub async fn serve(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let (asset, fingerprinted) = match BY_PATH.get(path.as_str()) {
+        Some(found) => *found,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let (encoding, body) = representation(asset, &headers);
+    // each encoding is a different representation with its own tag
+    let etag = match encoding {
+        Encoding::Identity => format!("\"{}\"", asset.hash),
+        Encoding::Gzip => format!("\"{}-gz\"", asset.hash),
+        Encoding::Brotli => format!("\"{}-br\"", asset.hash),
+    };
+    let cache_control = if fingerprinted { IMMUTABLE } else { REVALIDATE };
+
+    let mut response = if not_modified(&headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let mut response = Body::from(Bytes::from_static(body)).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(asset.content_type));
+        let content_encoding = match encoding {
+            Encoding::Identity => None,
+            Encoding::Gzip => Some("gzip"),
+            Encoding::Brotli => Some("br"),
+        };
+        if let Some(content_encoding) = content_encoding {
+            response
+                .headers_mut()
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(content_encoding));
+        }
+        response
+    };
+    let response_headers = response.headers_mut();
+    if let Ok(etag) = etag.parse() {
+        response_headers.insert(ETAG, etag);
+    }
+    response_headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    response_headers.insert(VARY, ACCEPT_ENCODING.into());
+    response
+}
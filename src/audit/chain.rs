@@ -0,0 +1,205 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::audit::models::{AuditCheckpoint, AuditEvent, NewAuditEvent};
+use crate::audit::repository::AuditRepository;
+use crate::config;
+use crate::utils::db::QueryError;
+
+/// A signed checkpoint is written every `CHECKPOINT_INTERVAL` chain positions.
+pub const CHECKPOINT_INTERVAL: i64 = 100;
+const VERIFY_BATCH: i64 = 1000;
+
+static SIGNING_KEY: Lazy<Vec<u8>> = Lazy::new(|| {
+    config::get().audit_signing_key().expose().as_bytes().to_vec()
+});
+
+/// Hash of an event's contents chained to the previous record's hash.
+pub fn event_hash(
+    prev_hash: Option<&str>,
+    created_at: &DateTime<Utc>,
+    event: &NewAuditEvent,
+) -> String {
+    // a JSON array gives an unambiguous encoding of optional fields
+    let content = serde_json::json!([
+        prev_hash,
+        created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+        event.event,
+        event.actor_id,
+        event.subject_id,
+        event.subject,
+        event.ip,
+        event.user_agent,
+        event.request_id,
+        event.details,
+    ]);
+    hex::encode(Sha256::digest(content.to_string().as_bytes()))
+}
+
+pub fn checkpoint_signature(event_id: i64, hash: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&SIGNING_KEY).expect("HMAC accepts any key length");
+    mac.update(format!("{}:{}", event_id, hash).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn verify_signature(event_id: i64, hash: &str, signature: &str) -> bool {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&SIGNING_KEY).expect("HMAC accepts any key length");
+    mac.update(format!("{}:{}", event_id, hash).as_bytes());
+    match hex::decode(signature) {
+        Ok(signature) => mac.verify_slice(&signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Timestamp and hash of `event` appended after `prev_hash`.
+pub fn link(prev_hash: Option<&str>, event: &NewAuditEvent) -> (DateTime<Utc>, String) {
+    // Postgres stores microseconds; truncate so the stored value hashes the same
+    let now = Utc::now();
+    let created_at = DateTime::from_timestamp_micros(now.timestamp_micros()).unwrap_or(now);
+    let hash = event_hash(prev_hash, &created_at, event);
+    (created_at, hash)
+}
+
+/// Signature of the checkpoint to store with event `event_id` at chain
+/// `position`, when one is due.
+///
+/// Checkpoints follow the position rather than the id, which may skip values.
+pub fn checkpoint(position: i64, event_id: i64, hash: &str) -> Option<String> {
+    (position % CHECKPOINT_INTERVAL == 0).then(|| checkpoint_signature(event_id, hash))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenLink {
+    pub event_id: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    pub events_checked: u64,
+    /// Leading records written before chaining, which nothing protects.
+    pub unchained_events: u64,
+    pub checkpoints_checked: u64,
+    pub last_event_id: Option<i64>,
+    pub first_broken: Option<BrokenLink>,
+}
+
+impl VerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.first_broken.is_none()
+    }
+}
+
+fn check_event(event: &AuditEvent, expected_prev: Option<&str>, position: i64) -> Result<(), String> {
+    let hash = match &event.hash {
+        Some(hash) => hash,
+        None => return Err("record inside the chain has no hash".to_string()),
+    };
+    if event.prev_hash.as_deref() != expected_prev {
+        return Err("previous hash does not match the preceding record".to_string());
+    }
+    if event.chain_position.is_some_and(|stored| stored != position) {
+        return Err(format!("record is stored at chain position {:?}, expected {}", event.chain_position, position));
+    }
+    let new_event = NewAuditEvent {
+        event: event.event.clone(),
+        actor_id: event.actor_id,
+        subject_id: event.subject_id,
+        subject: event.subject.clone(),
+        ip: event.ip.clone(),
+        user_agent: event.user_agent.clone(),
+        request_id: event.request_id.clone(),
+        details: event.details.clone(),
+    };
+    if &event_hash(event.prev_hash.as_deref(), &event.created_at, &new_event) != hash {
+        return Err("record contents do not match its hash".to_string());
+    }
+    Ok(())
+}
+
+fn check_checkpoint(checkpoint: &AuditCheckpoint, event: Option<&AuditEvent>) -> Result<(), String> {
+    if !verify_signature(checkpoint.event_id, &checkpoint.hash, &checkpoint.signature) {
+        return Err(format!("checkpoint {} has an invalid signature", checkpoint.id));
+    }
+    match event.and_then(|event| event.hash.as_deref()) {
+        Some(hash) if hash == checkpoint.hash => Ok(()),
+        Some(_) => Err(format!("checkpoint {} does not match the record hash", checkpoint.id)),
+        None => Err(format!("checkpoint {} refers to a missing record", checkpoint.id)),
+    }
+}
+
+/// Walk the whole chain and report the first broken link.
+///
+/// The hashes alone only show that the chain is consistent; anyone able to
+/// write to the database can recompute them. The signed checkpoints, one of
+/// which must exist at every `CHECKPOINT_INTERVAL` positions, are what tie
+/// the chain to the signing key.
+pub async fn verify(audit: &dyn AuditRepository) -> Result<VerifyReport, QueryError> {
+    let mut report = VerifyReport::default();
+    let mut checkpoints = audit.checkpoints().await?.into_iter().peekable();
+
+    let mut prev_hash: Option<String> = None;
+    let mut position = 0;
+    let mut after_id = 0;
+    // records written before chaining was enabled have no hash, but only
+    // until the first chained record
+    let mut started = false;
+    loop {
+        let events = audit.chain(after_id, VERIFY_BATCH).await?;
+        if events.is_empty() {
+            break;
+        }
+        for event in events {
+            after_id = event.id;
+            if !started && event.hash.is_none() && event.prev_hash.is_none() && event.chain_position.is_none() {
+                report.unchained_events += 1;
+                continue;
+            }
+            started = true;
+            position += 1;
+            report.events_checked += 1;
+            report.last_event_id = Some(event.id);
+
+            if let Err(reason) = check_event(&event, prev_hash.as_deref(), position) {
+                report.first_broken = Some(BrokenLink { event_id: event.id, reason });
+                return Ok(report);
+            }
+            let mut sealed = false;
+            while let Some(checkpoint) = checkpoints.next_if(|c| c.event_id <= event.id) {
+                report.checkpoints_checked += 1;
+                let covered = (checkpoint.event_id == event.id).then_some(&event);
+                if let Err(reason) = check_checkpoint(&checkpoint, covered) {
+                    report.first_broken = Some(BrokenLink { event_id: checkpoint.event_id, reason });
+                    return Ok(report);
+                }
+                sealed |= covered.is_some();
+            }
+            // records from before positions were stored got their
+            // checkpoints by id, and the append-only triggers keep it that way
+            let due = match event.chain_position {
+                Some(_) => position % CHECKPOINT_INTERVAL == 0,
+                None => event.id % CHECKPOINT_INTERVAL == 0,
+            };
+            if due && !sealed {
+                report.first_broken = Some(BrokenLink {
+                    event_id: event.id,
+                    reason: format!("checkpoint missing at chain position {}", position),
+                });
+                return Ok(report);
+            }
+            prev_hash = event.hash.clone();
+        }
+    }
+
+    // a checkpoint past the end of the chain means records were removed
+    if let Some(checkpoint) = checkpoints.next() {
+        report.first_broken = Some(BrokenLink {
+            event_id: checkpoint.event_id,
+            reason: format!("checkpoint {} refers to a missing record", checkpoint.id),
+        });
+    }
+    Ok(report)
+}
@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::stream::{self, StreamExt};
+use tracing::error;
+
+use crate::audit::chain::verify;
+use crate::audit::log::record;
+use crate::audit::models::{AuditEvent, AuditFilter, NewAuditEvent};
+use crate::audit::repository::AuditRepository;
+use crate::common::{page_context, render, AuthenticatedUser, RequestMeta, Templates};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::utils::db::QueryError;
+
+const PAGE_LIMIT: i64 = 200;
+const EXPORT_LIMIT: i64 = 100_000;
+const EXPORT_BATCH: i64 = 1000;
+const CSV_HEADER: &str = "id,created_at,event,actor_id,subject_id,subject,ip,user_agent,request_id,details\n";
+
+pub async fn audit_events(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    Query(filter): Query<AuditFilter>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
+    context.insert("filter", &filter);
+
+    let limit = filter.limit.unwrap_or(PAGE_LIMIT).clamp(1, PAGE_LIMIT);
+    let events = state.audit.events(&filter, limit).await?;
+    context.insert("events", &events);
+    render(&templates, "admin-audit", &context)
+}
+
+/// Events matching the filter as CSV or JSON Lines, newest first.
+///
+/// Rows are read and sent a batch at a time, so an export of up to
+/// `EXPORT_LIMIT` events never sits in memory whole.
+pub async fn export_audit_events(
+    State(state): State<AppState>,
+    Query(filter): Query<AuditFilter>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<Response, AppError> {
+    let limit = filter.limit.unwrap_or(EXPORT_LIMIT).clamp(1, EXPORT_LIMIT);
+    let jsonl = filter.format.as_deref() == Some("jsonl");
+    // read before the export is recorded, so that it does not list itself
+    let first = state.audit.events(&filter, limit.min(EXPORT_BATCH)).await?;
+
+    record(state.audit.as_ref(), NewAuditEvent::new("admin.audit_export", &meta)
+        .actor(user.id)
+        .details(&format!("format={} limit={}", if jsonl { "jsonl" } else { "csv" }, limit))).await;
+
+    let (content_type, extension, head) = if jsonl {
+        ("application/x-ndjson", "jsonl", String::new())
+    } else {
+        ("text/csv; charset=utf-8", "csv", CSV_HEADER.to_string())
+    };
+    let export = Export {
+        audit: state.audit.clone(),
+        filter,
+        remaining: limit,
+        batch: Some(first),
+    };
+    let rows = stream::unfold(export, move |mut export| async move {
+        let events = match export.next_batch().await {
+            Ok(events) if events.is_empty() => return None,
+            Ok(events) => events,
+            Err(e) => {
+                // the status is already sent; cutting the body short is all that is left
+                error!("Audit export failed: {:?}", e);
+                export.remaining = 0;
+                return Some((Err(e), export));
+            }
+        };
+        let chunk = if jsonl { to_jsonl(&events) } else { to_csv(&events) };
+        Some((Ok(chunk), export))
+    });
+    let body = stream::once(async move { Ok(head) }).chain(rows);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"audit-events.{}\"", extension),
+            ),
+        ],
+        Body::from_stream(body),
+    ).into_response())
+}
+
+pub async fn verify_audit_chain(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
+
+    let report = verify(state.audit.as_ref()).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("admin.audit_verify", &meta)
+        .actor(user.id)
+        .details(if report.is_intact() { "intact" } else { "broken" })).await;
+
+    context.insert("report", &report);
+    render(&templates, "admin-audit-verify", &context)
+}
+
+/// Where a streamed export has got to.
+struct Export {
+    audit: Arc<dyn AuditRepository>,
+    filter: AuditFilter,
+    /// Rows still to send before the limit is reached.
+    remaining: i64,
+    /// A batch read ahead of the stream.
+    batch: Option<Vec<AuditEvent>>,
+}
+
+impl Export {
+    /// The next batch of rows; empty once the export is complete.
+    async fn next_batch(&mut self) -> Result<Vec<AuditEvent>, QueryError> {
+        let wanted = self.remaining.min(EXPORT_BATCH);
+        let events = match self.batch.take() {
+            Some(events) => events,
+            None if wanted <= 0 => return Ok(Vec::new()),
+            None => self.audit.events(&self.filter, wanted).await?,
+        };
+        // a short batch is the last one
+        self.remaining = if (events.len() as i64) < wanted { 0 } else { self.remaining - events.len() as i64 };
+        self.filter.before_id = events.last().map(|event| event.id);
+        Ok(events)
+    }
+}
+
+fn to_jsonl(events: &[AuditEvent]) -> String {
+    events
+        .iter()
+        .filter_map(|event| serde_json::to_string(event).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// A CSV field, quoted when needed. Text that a spreadsheet would run as a
+/// formula gets a leading `'`; user agents and subjects are attacker-chosen.
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Rows for `events`, without the header.
+fn to_csv(events: &[AuditEvent]) -> String {
+    let mut out = String::new();
+    for event in events {
+        let fields = [
+            event.id.to_string(),
+            event.created_at.to_rfc3339(),
+            event.event.clone(),
+            event.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+            event.subject_id.map(|id| id.to_string()).unwrap_or_default(),
+            event.subject.clone().unwrap_or_default(),
+            event.ip.clone().unwrap_or_default(),
+            event.user_agent.clone().unwrap_or_default(),
+            event.request_id.clone().unwrap_or_default(),
+            event.details.clone().unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
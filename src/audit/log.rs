@@ -0,0 +1,24 @@
+use tracing::{error, info};
+
+use crate::audit::models::NewAuditEvent;
+use crate::audit::repository::AuditRepository;
+
+/// Append a security event to the audit log.
+///
+/// Failures are logged but never surface to the user: an unavailable audit
+/// table must not lock people out of their accounts.
+pub async fn record(audit: &dyn AuditRepository, event: NewAuditEvent) {
+    info!(
+        target: "audit",
+        event = %event.event,
+        actor_id = ?event.actor_id,
+        subject_id = ?event.subject_id,
+        subject = ?event.subject,
+        ip = ?event.ip,
+        request_id = ?event.request_id,
+        "{}", event.event
+    );
+    if let Err(e) = audit.append(&event).await {
+        error!("Failed to write audit event: {:?}", e);
+    }
+}
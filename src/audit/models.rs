@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::common::RequestMeta;
+use crate::utils::date_config::date_format;
+
+/// A row of the append-only `audit_events` table.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuditEvent {
+    pub id: i64,
+    #[serde(with = "date_format")]
+    pub created_at: DateTime<Utc>,
+    pub event: String,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub subject: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub details: Option<String>,
+    pub prev_hash: Option<String>,
+    pub hash: Option<String>,
+    /// 1-based position in the chain; `None` on records written before it was stored.
+    pub chain_position: Option<i64>,
+}
+
+/// A signed snapshot of the audit chain head.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuditCheckpoint {
+    pub id: i64,
+    pub event_id: i64,
+    pub hash: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NewAuditEvent {
+    pub event: String,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub subject: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub details: Option<String>,
+}
+
+impl NewAuditEvent {
+    pub fn new(event: &str, meta: &RequestMeta) -> Self {
+        NewAuditEvent {
+            event: event.to_string(),
+            ip: meta.ip.clone(),
+            user_agent: meta.user_agent.clone(),
+            request_id: meta.request_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn actor(mut self, id: i32) -> Self {
+        self.actor_id = Some(id);
+        self
+    }
+
+    pub fn subject_id(mut self, id: i32) -> Self {
+        self.subject_id = Some(id);
+        self
+    }
+
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn details(mut self, details: &str) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AuditFilter {
+    pub event: Option<String>,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub subject: Option<String>,
+    pub limit: Option<i64>,
+    pub format: Option<String>,
+    /// Only events older than this one, for reading past a previous batch.
+    #[serde(skip)]
+    pub before_id: Option<i64>,
+}
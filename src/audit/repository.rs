@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+
+use crate::audit::models::{AuditCheckpoint, AuditEvent, AuditFilter, NewAuditEvent};
+use crate::utils::db::QueryError;
+
+/// Storage of the append-only audit chain.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    /// Append an event sealed with `chain::link`, plus a checkpoint when
+    /// `chain::checkpoint` asks for one, returning the event id.
+    ///
+    /// Appends must be serialized so that concurrent writers cannot fork the chain.
+    async fn append(&self, event: &NewAuditEvent) -> Result<i64, QueryError>;
+
+    /// A batch of events in chain order.
+    async fn chain(&self, after_id: i64, limit: i64) -> Result<Vec<AuditEvent>, QueryError>;
+
+    /// Every checkpoint in chain order.
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, QueryError>;
+
+    /// Events matching the filter, newest first.
+    async fn events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditEvent>, QueryError>;
+}
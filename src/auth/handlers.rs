@@ -2,63 +2,59 @@ use axum::{
     Extension,
     extract::{Form, State}
     ,
-    response::{Html, IntoResponse},
+    response::{IntoResponse, Response},
 };
 use axum::response::Redirect;
 use chrono::Utc;
 use tera::Context;
 use validator::Validate;
 
-use crate::auth::models::{FormLogin, User};
-use crate::common::{build_redirect_with_cookie, html_err, Templates};
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::auth::models::FormLogin;
+use crate::common::{build_redirect_with_cookie, check_email_sent, html_err, page_context, render, AuthenticatedUser, RequestMeta, Templates};
+use crate::error::AppError;
+use crate::metrics;
 use crate::profile::models::{FormSingUpUser, NewUser};
 use crate::state::AppState;
 use crate::utils::date_option::get_max_age_seconds;
-use crate::utils::db::{check_email, check_username, get_user, query_new_user};
-use crate::utils::jwt::{ar_hash_password, ar_verify_password, encode_jwt};
-use crate::utils::message::handle_errors;
+use crate::utils::flash::Flash;
+use crate::utils::jwt::{ar_dummy_verify, ar_hash_password, ar_verify_password, encode_jwt, encode_session};
+use crate::utils::mail;
+use crate::utils::message::{field_error, handle_errors};
+
+const INVALID_CREDENTIALS: &str = "login-invalid-credentials";
+const USERNAME_TAKEN: &str = "signup-username-taken";
+const ACCOUNT_DISABLED: &str = "login-account-disabled";
 
 pub async fn get_signup(
     Extension(templates): Extension<Templates>,
-) -> impl IntoResponse {
-    Html(templates.render("signup", &Context::new()).unwrap())
+) -> Result<impl IntoResponse, AppError> {
+    render(&templates, "signup", &Context::new())
 }
 
 pub async fn post_signup(
     State(state): State<AppState>,
     Extension(templates): Extension<Templates>,
+    meta: RequestMeta,
     Form(form): Form<FormSingUpUser>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
     if let Err(errors) = form.validate() {
-        context.insert("messages", &handle_errors(errors).await);
-        return Err(Html(templates.render("signup", &context).unwrap()));
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "signup", &context)?.into_response());
     }
 
-    if let Ok(true) = check_email(&state.db, form.email.clone()).await {
-        return Err(html_err(
-            &templates,
-            "signup",
-            &mut context,
-            format!("Email already exists: {:?}", form.email),
-        ).await);
-    }
+    // hash before looking anything up so every outcome costs the same
+    let hashed_password = ar_hash_password(&form.password)?;
 
-    if let Ok(true) = check_username(&state.db, form.email.clone()).await {
-        return Err(html_err(
-            &templates,
-            "signup",
-            &mut context,
-            format!("Username already exists: {:?}", form.username),
-        ).await);
+    // advisory only: the unique constraints below are what decide
+    if state.users.username_exists(&form.username).await? {
+        context.insert("field_errors", &field_error("username", &templates.t(USERNAME_TAKEN)));
+        return Ok(render(&templates, "signup", &context)?.into_response());
     }
 
-    let hashed_password = match ar_hash_password(&form.password.clone()) {
-        Ok(hashed) => hashed,
-        Err(e) => return Err(html_err(&templates, "signup", &mut context, format!("Hashed Error: {:?}", e)).await)
-    };
-
     let new_user = NewUser {
         email: form.email,
         username: form.username,
@@ -67,95 +63,121 @@ pub async fn post_signup(
         created_at: Utc::now(),
     };
 
-    if let Err(e) = query_new_user(&state.db, new_user.clone()).await {
-        return Err(html_err(
-            &templates,
-            "signup",
-            &mut context,
-            format!("Error Create: {:?}", e),
-        ).await);
+    // whether the account exists is only ever told to the mailbox owner;
+    // the page is the same in every case
+    if let Err(e) = state.users.create(new_user.clone()).await {
+        return match e.user_field() {
+            Some("email") => {
+                record(state.audit.as_ref(), NewAuditEvent::new("signup.duplicate", &meta)
+                    .subject(&new_user.email)
+                    .details("email exists")).await;
+                mail::send(mail::account_exists(&new_user.email));
+                check_email_sent(&templates)
+            }
+            Some("username") => {
+                context.insert("field_errors", &field_error("username", &templates.t(USERNAME_TAKEN)));
+                Ok(render(&templates, "signup", &context)?.into_response())
+            }
+            _ => Err(e.into()),
+        };
     }
+    record(state.audit.as_ref(), NewAuditEvent::new("signup", &meta).subject(&new_user.email)).await;
 
-    match encode_jwt(new_user.email.clone(), "email-verify".to_string(), 1).await {
-        Ok(token) => {
-            //  this need send email
-            context.insert("verify", &token);
-            Ok(Html(templates.render("email-verify", &context).unwrap()))
-        }
-        Err(e) => {
-            return Err(html_err(
-                &templates,
-                "signup",
-                &mut context,
-                format!("Error encode jwt: {:?}", e),
-            ).await);
-        }
-    }
+    let token = encode_jwt(new_user.email.clone(), "email-verify".to_string(), 1)
+        .await
+        .map_err(AppError::Jwt)?;
+    mail::send(mail::verify_email(&new_user.email, &token));
+    check_email_sent(&templates)
 }
 
 pub async fn get_login(
     Extension(templates): Extension<Templates>,
-) -> impl IntoResponse {
-    Html(templates.render("login", &Context::new()).unwrap())
+) -> Result<impl IntoResponse, AppError> {
+    render(&templates, "login", &Context::new())
 }
 
 pub async fn post_login(
     State(state): State<AppState>,
     Extension(templates): Extension<Templates>,
+    flash: Flash,
+    meta: RequestMeta,
     Form(form): Form<FormLogin>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
     if let Err(errors) = form.validate() {
-        context.insert("messages", &handle_errors(errors).await);
-        return Err(Html(templates.render("login", &context).unwrap()));
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "login", &context)?.into_response());
     }
 
-    let user = match get_user(&state.db, form.email.clone()).await {
-        Ok(row) => User::from_row(&row),
-        Err(e) => return Err(html_err(
-            &templates,
-            "signup",
-            &mut context,
-            format!("Error Get User: {:?}", e),
-        ).await)
+    // unknown logins and wrong passwords get the same message and cost
+    let user = match state.users.find_by_login(&form.login).await {
+        Ok(user) => user,
+        Err(_) => {
+            ar_dummy_verify(&form.password);
+            metrics::login_attempt("web", false);
+            record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+                .subject(&form.login)
+                .details("unknown user")).await;
+            return html_err(&templates, "login", &mut context, templates.t(INVALID_CREDENTIALS)).await;
+        }
     };
 
-    match ar_verify_password(&form.password, &user.password) {
-        Ok(_) => {}
-        Err(e) => return Err(html_err(
-            &templates,
-            "signup",
-            &mut context,
-            format!("Error Verify Password: {:?}", e),
-        ).await)
+    if !matches!(ar_verify_password(&form.password, &user.password), Ok(true)) {
+        metrics::login_attempt("web", false);
+        record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details("invalid password")).await;
+        return html_err(&templates, "login", &mut context, templates.t(INVALID_CREDENTIALS)).await;
+    }
+    if user.is_disabled() {
+        metrics::login_attempt("web", false);
+        record(state.audit.as_ref(), NewAuditEvent::new("login.failure", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details("account disabled")).await;
+        return html_err(&templates, "login", &mut context, templates.t(ACCOUNT_DISABLED)).await;
     }
     if !user.is_verify {
+        metrics::login_attempt("web", false);
+        flash.info(templates.t("flash-verify-first"));
         return Ok(Redirect::to("/account/email-verify-resend").into_response());
     }
-    let token = match encode_jwt(user.email.clone(), "auth".to_string(), 12).await {
-        Ok(token) => token,
-        Err(e) => {
-            eprintln!("JWT creation failed: {:?}", e); // Log the error
-            return Err(html_err(
-                &templates,
-                "login",
-                &mut context,
-                "An error occurred during login. Please try again.".to_string(),
-            ).await);
-        }
-    };
+    let token = encode_session(user.email.clone())
+        .await
+        .map_err(AppError::Jwt)?;
+    metrics::login_attempt("web", true);
+    record(state.audit.as_ref(), NewAuditEvent::new("login.success", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
     Ok(build_redirect_with_cookie(&token, get_max_age_seconds(), "/account/detail"))
 }
 
 pub async fn get_logout(
     Extension(templates): Extension<Templates>,
-) -> impl IntoResponse {
-    Html(templates.render("logout", &Context::new()).unwrap())
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    render(&templates, "logout", &page_context(&user))
 }
 
 
 pub async fn post_logout(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
 ) -> impl IntoResponse {
+    let mut event = NewAuditEvent::new("logout", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email);
+    if let Some(impersonator) = &user.impersonator {
+        event = event.actor(impersonator.id);
+    }
+    record(state.audit.as_ref(), event).await;
+    flash.info(templates.t("flash-logged-out"));
     build_redirect_with_cookie("", "0".to_string(), "/account/login")
-}
\ No newline at end of file
+}
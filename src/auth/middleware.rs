@@ -1,38 +1,146 @@
-use axum::extract::{Request, State};
-use axum::http::header::COOKIE;
+use axum::extract::{OriginalUri, Request, State};
+use axum::http::header::{AUTHORIZATION, COOKIE};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Redirect, Response};
-use tracing::{error, info};
-use crate::auth::models::User;
-use crate::common::AuthenticatedUser;
+use tracing::{info, warn, Span};
+use crate::auth::models::Claims;
+use crate::api::models::ApiError;
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::common::{AuthenticatedUser, Impersonator, RequestMeta};
+use crate::error::AppError;
 use crate::state::AppState;
+use crate::tokens::secret;
 use crate::utils::cookie::extract_cookie_value;
-use crate::utils::db::get_user;
 use crate::utils::jwt::decode_token;
 
-async fn clear_current_user(state: &AppState) {
-    if let Ok(mut user) = state.user.lock() {
-        *user = AuthenticatedUser::default();
-    } else {
-        error!("Failed to acquire lock to clear current user.");
+/// Resolve the user behind a session token, checking impersonation claims
+/// against the admin who issued them.
+async fn resolve_user(state: &AppState, claims: &Claims) -> Option<AuthenticatedUser> {
+    // email-verify and reset-password tokens must not open a session
+    if claims.purpose != "auth" && claims.purpose != "impersonate" {
+        return None;
+    }
+
+    let user = match state.users.find_by_email(&claims.email).await {
+        Ok(user) => user,
+        Err(_) => {
+            info!("User details not found for email: {:?}", claims.email);
+            return None;
+        }
+    };
+    if user.is_disabled() || user.session_revoked(claims.iat) {
+        info!("Rejected revoked session for user {}", user.id);
+        return None;
     }
+
+    let impersonator = match claims.impersonator_id {
+        Some(admin_id) => {
+            if claims.purpose != "impersonate" || claims.user_id != Some(user.id) {
+                warn!("Rejected malformed impersonation token for user {}", user.id);
+                return None;
+            }
+            let admin = match state.users.find_by_id(admin_id).await {
+                Ok(user) => user,
+                Err(_) => return None,
+            };
+            if !admin.is_admin || admin.is_disabled() {
+                warn!("Rejected impersonation token issued by non-admin {}", admin.id);
+                return None;
+            }
+            // ending the admin's sessions ends the impersonations they started
+            if admin.session_revoked(claims.iat) {
+                info!("Rejected impersonation token revoked by admin {}", admin.id);
+                return None;
+            }
+            Some(Impersonator {
+                id: admin.id,
+                email: admin.email,
+                username: admin.username,
+            })
+        }
+        None => None,
+    };
+
+    Some(AuthenticatedUser {
+        id: user.id,
+        email: user.email,
+        username: user.username,
+        is_admin: user.is_admin,
+        purpose: claims.purpose.clone(),
+        impersonator,
+        bearer: false,
+        scopes: None,
+        locale: user.locale,
+    })
 }
 
-async fn update_current_user(state: &AppState, claims: AuthenticatedUser) {
-    if let Ok(mut user) = state.user.lock() {
-        *user = claims;
-    } else {
-        error!("Failed to acquire lock to update current user.");
+/// Resolve the user behind a personal access token, recording its use.
+async fn resolve_access_token(state: &AppState, token: &str, meta: &RequestMeta) -> Option<AuthenticatedUser> {
+    let prefix = secret::parse_prefix(token)?;
+    let api_token = match state.tokens.find_by_prefix(prefix).await {
+        Ok(api_token) => api_token,
+        Err(_) => return None,
+    };
+    if api_token.token_hash != secret::hash(token) || !api_token.is_active {
+        info!("Rejected personal access token {}", api_token.prefix);
+        return None;
+    }
+
+    let user = match state.users.find_by_id(api_token.user_id).await {
+        Ok(user) => user,
+        Err(_) => return None,
+    };
+    if user.is_disabled() {
+        return None;
     }
+    if let Err(e) = state.tokens.touch(api_token.id, meta.ip.as_deref()).await {
+        warn!("Failed to record use of token {}: {:?}", api_token.prefix, e);
+    }
+
+    Some(AuthenticatedUser {
+        id: user.id,
+        email: user.email,
+        username: user.username,
+        is_admin: user.is_admin,
+        purpose: "token".to_string(),
+        impersonator: None,
+        bearer: true,
+        scopes: Some(api_token.scopes),
+        locale: user.locale,
+    })
+}
+
+/// Session token from the `Authorization: Bearer` header, if any.
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
 }
 
 pub async fn cookie_to_state(
     State(state): State<AppState>,
-    request: Request,
+    meta: RequestMeta,
+    mut request: Request,
     next: Next,
 ) -> Response {
-    // Clear the current user before processing the request
-    clear_current_user(&state).await;
+    // API clients send the token in a header, browsers in the "visit" cookie
+    let bearer = bearer_token(&request);
+    let is_bearer = bearer.is_some();
+
+    // personal access tokens only ever open the JSON API
+    if let Some(token) = bearer.as_deref().filter(|token| token.starts_with(secret::TOKEN_PREFIX)) {
+        if request.uri().path().starts_with("/api/") {
+            if let Some(user) = resolve_access_token(&state, token, &meta).await {
+                record_user(&user);
+                request.extensions_mut().insert(user);
+            }
+        }
+        return next.run(request).await;
+    }
 
     // Get the COOKIE header from the request
     let cookie_header = request
@@ -42,7 +150,7 @@ pub async fn cookie_to_state(
         .unwrap_or_default();
 
     // Extract the "visit" token from the cookie
-    let token = match extract_cookie_value(cookie_header, "visit") {
+    let token = match bearer.or_else(|| extract_cookie_value(cookie_header, "visit")) {
         Some(token) => token,
         None => return next.run(request).await, // Return the response if no token is found
     };
@@ -53,64 +161,118 @@ pub async fn cookie_to_state(
         Ok(None) | Err(_) => return next.run(request).await, // Return the response if decoding fails
     };
 
-    // Fetch the user details based on the claims
-    match get_user(&state.db, claims.email.clone()).await {
-        Ok(row) => {
-            let user = User::from_row(&row);
-            info!("User details found: {:?}", user);
-            update_current_user(&state, AuthenticatedUser {
-                email: user.email,
-                purpose: "".to_string(),
-            }).await;
-        }
-        Err(_) => {
-            info!("User details not found for email: {:?}", claims.email);
+    // impersonation only ever happens in the admin's browser; as a bearer
+    // credential it would reach the API, where nothing is guarded against it
+    if is_bearer && (claims.purpose == "impersonate" || claims.impersonator_id.is_some()) {
+        let mut event = NewAuditEvent::new("impersonation.blocked", &meta)
+            .subject(&claims.email)
+            .details("bearer token");
+        if let Some(id) = claims.impersonator_id {
+            event = event.actor(id);
         }
-    };
+        record(state.audit.as_ref(), event).await;
+        return ApiError::Unauthorized.into_response();
+    }
+
+    // Fetch the user details based on the claims
+    if let Some(mut user) = resolve_user(&state, &claims).await {
+        info!("User details found: {:?}", user);
+        user.bearer = is_bearer;
+        record_user(&user);
+        request.extensions_mut().insert(user);
+    }
 
     next.run(request).await
 }
 
+/// Tag the request span, and so every log line of the request, with the user.
+fn record_user(user: &AuthenticatedUser) {
+    let span = Span::current();
+    span.record("user_id", user.id);
+    if let Some(impersonator) = &user.impersonator {
+        span.record("impersonator_id", impersonator.id);
+    }
+}
+
+fn current_user(request: &Request) -> Option<&AuthenticatedUser> {
+    request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .filter(|user| user.is_authenticated())
+}
+
 pub async fn require_auth(
-    State(state): State<AppState>,
     request: Request,
     next: Next,
 ) -> Response {
-    match state.user.lock() {
-        Ok(user) => {
-            if user.email.is_empty() {
-                info!("User is not authenticated. Redirecting to login page.");
-                return Redirect::to("/account/login").into_response();
-            }
-        }
-        Err(_) => {
-            info!("Failed to acquire lock to check current user.");
-            return Redirect::to("/account/login").into_response();
-        }
+    if current_user(&request).is_none() {
+        info!("User is not authenticated. Redirecting to login page.");
+        return Redirect::to("/account/login").into_response();
     }
     next.run(request).await
 }
 
 pub async fn require_guest(
-    State(state): State<AppState>,
     request: Request,
     next: Next,
 ) -> Response {
-    // Attempt to acquire the lock to check the user state
-    let is_authenticated = match state.user.lock() {
-        Ok(user) => !user.email.is_empty(), // User is authenticated if email is not empty
-        Err(_) => {
-            info!("Failed to acquire lock to check current user.");
-            // Handle lock acquisition failure by redirecting to logout page
-            return Redirect::to("/account/logout").into_response();
-        }
-    };
     // If the user is authenticated, redirect to the logout page
-    if is_authenticated {
+    if current_user(&request).is_some() {
         info!("User is authenticated. Redirecting to logout.");
         Redirect::to("/account/logout").into_response()
     } else {
         // Otherwise, proceed with the request
         next.run(request).await
     }
-}
\ No newline at end of file
+}
+
+/// API routes only accept bearer sessions; cookies would expose them to CSRF.
+/// Impersonation sessions are never let through.
+pub async fn require_api_auth(
+    request: Request,
+    next: Next,
+) -> Response {
+    match current_user(&request) {
+        Some(user) if user.bearer && !user.is_impersonating() => next.run(request).await,
+        _ => ApiError::Unauthorized.into_response(),
+    }
+}
+
+pub async fn require_admin(
+    request: Request,
+    next: Next,
+) -> Response {
+    match current_user(&request) {
+        Some(user) if user.is_admin && !user.is_impersonating() => next.run(request).await,
+        Some(user) => {
+            info!("User {} is not an admin.", user.id);
+            AppError::Forbidden.into_response()
+        }
+        None => Redirect::to("/account/login").into_response(),
+    }
+}
+
+/// Block sensitive account actions while an admin is impersonating the user.
+pub async fn forbid_impersonation(
+    State(state): State<AppState>,
+    meta: RequestMeta,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(user) = current_user(&request).filter(|user| user.is_impersonating()) {
+        let path = request
+            .extensions()
+            .get::<OriginalUri>()
+            .map_or_else(|| request.uri().path(), |uri| uri.path());
+        let mut event = NewAuditEvent::new("impersonation.blocked", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details(path);
+        if let Some(Impersonator { id, .. }) = &user.impersonator {
+            event = event.actor(*id);
+        }
+        record(state.audit.as_ref(), event).await;
+        return AppError::Impersonating.into_response();
+    }
+    next.run(request).await
+}
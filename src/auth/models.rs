@@ -1,30 +1,41 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::profile::models::Visibility;
 use crate::utils::date_config::date_format;
 use chrono::serde::ts_seconds_option;
-use sqlx::postgres::PgRow;
-use sqlx::Row;
+use sqlx::FromRow;
+use utoipa::ToSchema;
 use validator_derive::Validate;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct ListUser {
     pub id: i32,
     pub email: String,
     pub username: String,
     pub img: Option<String>,
     pub is_verify: bool,
+    pub is_admin: bool,
     #[serde(with = "date_format")]
+    #[schema(value_type = String, example = "2024-07-08 05:40:05")]
     pub created_at: DateTime<Utc>,
     #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>, example = 1720417205)]
     pub updated_at: Option<DateTime<Utc>>,
+    pub bio: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub visibility: Visibility,
 }
 
-#[derive(Validate, Debug, Clone, Deserialize, Serialize)]
+#[derive(Validate, Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct FormLogin {
-    #[validate(email(message = "Email is not valid"))]
-    pub email: String,
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    /// A username or an email address; `email` is still accepted as the field name.
+    #[serde(alias = "email")]
+    #[validate(length(min = 1, code = "login-required"))]
+    #[schema(example = "ferris")]
+    pub login: String,
+    #[validate(length(min = 8, code = "password-length"))]
+    #[schema(min_length = 8, format = Password)]
     pub password: String,
 }
 
@@ -35,24 +46,44 @@ pub struct Claims {
     pub exp: usize,
     pub iat: usize,
     pub purpose: String,
+    /// Id of the user the token is for (set for impersonation and email change tokens).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    /// Id of the admin who started an impersonation session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<i32>,
 }
 
-impl User {
-    pub fn from_row(row: &PgRow) -> Self {
-        User {
-            id: row.get("id"),
-            email: row.get("email"),
-            username: row.get("username"),
-            img: row.get("img"),
-            is_verify: row.get("is_verify"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-            password: row.get("password"),
+impl From<User> for ListUser {
+    fn from(user: User) -> Self {
+        ListUser {
+            id: user.id,
+            email: user.email,
+            username: user.username,
+            img: user.img,
+            is_verify: user.is_verify,
+            is_admin: user.is_admin,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            bio: user.bio,
+            visibility: user.visibility,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl User {
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+
+    /// Whether a session token issued at `iat` (seconds) has been revoked.
+    pub fn session_revoked(&self, iat: usize) -> bool {
+        self.sessions_revoked_at
+            .is_some_and(|revoked_at| iat as i64 <= revoked_at.timestamp())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct User {
     pub id: i32,
     pub password: String,
@@ -60,8 +91,18 @@ pub struct User {
     pub username: String,
     pub img: Option<String>,
     pub is_verify: bool,
+    pub is_admin: bool,
     #[serde(with = "date_format")]
     pub created_at: DateTime<Utc>,
     #[serde(with = "ts_seconds_option")]
     pub updated_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub disabled_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub sessions_revoked_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub locale: Option<String>,
+    pub bio: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub visibility: Visibility,
 }
\ No newline at end of file
@@ -1,26 +1,69 @@
-use axum::extract::State;
-
-use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
-
-use crate::schema;
-use crate::{auth::models::ListUser, common::Pool};
-
-pub async fn full_auth(
-    State(pool): State<Pool>,
-    obj: String,
-) -> Result<Option<ListUser>, Option<String>> {
-    
-    let mut conn = pool.get().await.unwrap();
-    use schema::users::dsl::*;
-
-    let user: Option<ListUser> = users
-        .filter(email.eq(obj))
-        .select(ListUser::as_select())
-        .first::<ListUser>(&mut conn)
-        .await
-        .optional()
-        .unwrap();
-
-    Ok(user)
+use async_trait::async_trait;
+
+use crate::auth::models::{ListUser, User};
+use crate::directory::models::DirectoryFilter;
+use crate::profile::models::{NewUser, PasswordChange, UpdateUser, UpdateUserEmailVerify, Visibility};
+use crate::utils::db::QueryError;
+
+/// Storage of user accounts.
+///
+/// Whatever the backend, a missing user is `QueryError::RowNotFound` and a
+/// taken email or username is `QueryError::Duplicate` naming the
+/// `users_email_key` or `users_username_key` constraint.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// Whether the username is taken, ignoring case, so that a login by
+    /// username stays unambiguous.
+    async fn username_exists(&self, username: &str) -> Result<bool, QueryError>;
+
+    async fn find_by_email(&self, email: &str) -> Result<User, QueryError>;
+
+    /// A user by username or email, ignoring case; an exact match wins over
+    /// one that differs only in case.
+    async fn find_by_login(&self, login: &str) -> Result<User, QueryError>;
+
+    async fn find_by_username(&self, username: &str) -> Result<User, QueryError>;
+
+    async fn find_by_id(&self, id: i32) -> Result<User, QueryError>;
+
+    /// Every user ordered by id.
+    async fn list(&self) -> Result<Vec<ListUser>, QueryError>;
+
+    /// Up to `limit` users of the member directory, in the filter's order
+    /// and after its cursor.
+    async fn directory(&self, filter: &DirectoryFilter, limit: i64) -> Result<Vec<ListUser>, QueryError>;
+
+    async fn create(&self, user: NewUser) -> Result<(), QueryError>;
+
+    /// Update a user's email verification status.
+    async fn set_verified(&self, user: UpdateUserEmailVerify) -> Result<(), QueryError>;
+
+    async fn update_password(&self, user: PasswordChange) -> Result<(), QueryError>;
+
+    /// Update a user's email and username; changing the email clears verification.
+    async fn update_profile(&self, id: i32, user: UpdateUser) -> Result<(), QueryError>;
+
+    /// Replace a user's email with one confirmed from a link mailed to it.
+    async fn change_email(&self, id: i32, email: &str) -> Result<(), QueryError>;
+
+    /// Grant or withdraw admin rights.
+    async fn set_admin(&self, email: &str, is_admin: bool) -> Result<u64, QueryError>;
+
+    /// Disable or re-enable a user's account.
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<u64, QueryError>;
+
+    async fn set_locale(&self, id: i32, locale: &str) -> Result<u64, QueryError>;
+
+    /// Update what a user's public page shows and who can see it.
+    async fn set_public_profile(&self, id: i32, bio: Option<&str>, visibility: Visibility) -> Result<u64, QueryError>;
+
+    /// Delete a user together with their tokens.
+    async fn delete(&self, email: &str) -> Result<(), QueryError>;
+}
+
+/// Server-side state of session tokens, which are otherwise self-contained JWTs.
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    /// End every session token issued to a user so far.
+    async fn revoke_all(&self, email: &str) -> Result<u64, QueryError>;
 }
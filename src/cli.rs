@@ -0,0 +1,342 @@
+use std::io::BufRead;
+
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand};
+use rand_core::{OsRng, RngCore};
+use sqlx::migrate::MigrateError;
+use thiserror::Error;
+use validator::{Validate, ValidationErrors};
+
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::auth::models::User;
+use crate::auth::repository::UserRepository;
+use crate::common::RequestMeta;
+use crate::config::{self, Config, ConfigError};
+use crate::i18n::Locale;
+use crate::profile::models::{FormPasswordChange, FormSingUpUser, NewUser, PasswordChange, UpdateUserEmailVerify};
+use crate::storage::database::{Database, Store};
+use crate::utils::db::QueryError;
+use crate::utils::jwt::ar_hash_password;
+use crate::utils::migrate::{self, applied_versions, migrator, pending};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Account server and its maintenance tasks")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the web server (the default)
+    Serve,
+    /// Apply, revert or list database migrations
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Create a verified user
+    CreateUser(NewUserArgs),
+    /// Create a verified user with admin rights
+    CreateAdmin(NewUserArgs),
+    /// Set a user's password
+    SetPassword {
+        #[arg(long)]
+        email: String,
+        /// Read from the first line of stdin when omitted
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Mark a user's email address as verified
+    VerifyUser {
+        #[arg(long)]
+        email: String,
+    },
+    /// Disable an account, or re-enable it with --enable
+    DisableUser {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        enable: bool,
+    },
+    /// End every session a user currently has
+    RevokeSessions {
+        #[arg(long)]
+        email: String,
+    },
+    /// Print freshly generated secrets for the configuration
+    GenKeys,
+    /// Check the configuration, the database and pending migrations
+    Doctor,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateAction {
+    /// Apply every pending migration
+    Up,
+    /// Revert the latest migration, or every migration after --to
+    Down {
+        #[arg(long)]
+        to: Option<i64>,
+    },
+    /// List migrations and whether they are applied
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub struct NewUserArgs {
+    #[arg(long)]
+    email: String,
+    #[arg(long)]
+    username: String,
+    /// Read from the first line of stdin when omitted
+    #[arg(long)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("migrations: {0}")]
+    Migrate(#[from] MigrateError),
+    #[error("database: {0}")]
+    Query(#[from] QueryError),
+    #[error("password hashing failed: {0}")]
+    Password(argon2::password_hash::Error),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("no user with email {0}")]
+    UnknownUser(String),
+    #[error("{0} problem(s) found")]
+    Unhealthy(usize),
+}
+
+impl From<argon2::password_hash::Error> for CliError {
+    fn from(err: argon2::password_hash::Error) -> Self {
+        CliError::Password(err)
+    }
+}
+
+impl From<ValidationErrors> for CliError {
+    fn from(errors: ValidationErrors) -> Self {
+        let messages: Vec<String> = Locale::default()
+            .validation_messages(&errors)
+            .into_iter()
+            .flat_map(|(_, messages)| messages)
+            .collect();
+        CliError::Invalid(messages.join("; "))
+    }
+}
+
+/// Run a maintenance command; `serve` is handled by the binary.
+pub async fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Serve => Ok(()),
+        Command::GenKeys => {
+            gen_keys();
+            Ok(())
+        }
+        Command::Doctor => doctor().await,
+        Command::Migrate { action } => {
+            let db = connect().await?;
+            migrate(&db, action).await
+        }
+        Command::CreateUser(args) => create_user(&*connect().await?.store(), args, false).await,
+        Command::CreateAdmin(args) => create_user(&*connect().await?.store(), args, true).await,
+        Command::SetPassword { email, password } => {
+            let store = connect().await?.store();
+            let user = find_user(&*store, &email).await?;
+            let form = FormPasswordChange { password: password_or_stdin(password)? };
+            form.validate()?;
+            let password_change = PasswordChange {
+                email: user.email.clone(),
+                password: ar_hash_password(&form.password)?,
+                updated_at: Some(Utc::now()),
+            };
+            store.update_password(password_change).await?;
+            store.revoke_all(&user.email).await?;
+            audit(&*store, "password.set", &user).await;
+            println!("password set for {}, sessions revoked", user.email);
+            Ok(())
+        }
+        Command::VerifyUser { email } => {
+            let store = connect().await?.store();
+            let user = find_user(&*store, &email).await?;
+            let update = UpdateUserEmailVerify {
+                email: user.email.clone(),
+                is_verify: true,
+                updated_at: Some(Utc::now()),
+            };
+            store.set_verified(update).await?;
+            audit(&*store, "email.verify", &user).await;
+            println!("{} is verified", user.email);
+            Ok(())
+        }
+        Command::DisableUser { email, enable } => {
+            let store = connect().await?.store();
+            let user = find_user(&*store, &email).await?;
+            store.set_disabled(&user.email, !enable).await?;
+            audit(&*store, if enable { "account.enable" } else { "account.disable" }, &user).await;
+            println!("{} is {}", user.email, if enable { "enabled" } else { "disabled" });
+            Ok(())
+        }
+        Command::RevokeSessions { email } => {
+            let store = connect().await?.store();
+            let user = find_user(&*store, &email).await?;
+            store.revoke_all(&user.email).await?;
+            audit(&*store, "sessions.revoke", &user).await;
+            println!("sessions of {} revoked", user.email);
+            Ok(())
+        }
+    }
+}
+
+/// Load the configuration and open the database it names.
+async fn connect() -> Result<Database, CliError> {
+    let config = config::init(Config::load()?);
+    Ok(Database::connect(config.database_url.expose()).await?)
+}
+
+async fn find_user(store: &dyn Store, email: &str) -> Result<User, CliError> {
+    match store.find_by_email(email).await {
+        Ok(user) => Ok(user),
+        Err(QueryError::RowNotFound) => Err(CliError::UnknownUser(email.to_string())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn audit(store: &dyn Store, event: &str, user: &User) {
+    record(store, NewAuditEvent::new(event, &RequestMeta::default())
+        .subject_id(user.id)
+        .subject(&user.email)
+        .details("cli")).await;
+}
+
+fn password_or_stdin(password: Option<String>) -> Result<String, CliError> {
+    if let Some(password) = password {
+        return Ok(password);
+    }
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| CliError::Invalid(format!("cannot read the password from stdin: {}", e)))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+async fn create_user(store: &dyn Store, args: NewUserArgs, is_admin: bool) -> Result<(), CliError> {
+    let form = FormSingUpUser {
+        email: args.email,
+        username: args.username,
+        password: password_or_stdin(args.password)?,
+    };
+    form.validate()?;
+
+    let new_user = NewUser {
+        email: form.email,
+        username: form.username,
+        password: ar_hash_password(&form.password)?,
+        is_verify: true,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = UserRepository::create(store, new_user.clone()).await {
+        return Err(match e.user_field() {
+            Some(field) => CliError::Invalid(format!("this {} is already taken", field)),
+            None => e.into(),
+        });
+    }
+    if is_admin {
+        store.set_admin(&new_user.email, true).await?;
+    }
+
+    let user = find_user(store, &new_user.email).await?;
+    audit(store, if is_admin { "admin.create" } else { "user.create" }, &user).await;
+    println!("created {} {} (id {})", if is_admin { "admin" } else { "user" }, user.email, user.id);
+    Ok(())
+}
+
+async fn migrate(db: &Database, action: MigrateAction) -> Result<(), CliError> {
+    match action {
+        MigrateAction::Up => {
+            migrate::run(db).await?;
+            println!("database is up to date");
+        }
+        MigrateAction::Down { to } => {
+            let applied = applied_versions(db).await?;
+            let target = match to {
+                Some(target) => target,
+                // one step back: the version before the latest applied one
+                None => applied.iter().rev().nth(1).copied().unwrap_or(0),
+            };
+            migrate::undo(db, target).await?;
+            println!("reverted to {}", if target == 0 { "an empty database".to_string() } else { target.to_string() });
+        }
+        MigrateAction::Status => {
+            let applied = applied_versions(db).await?;
+            let migrations = migrator(db).into_iter().flat_map(|migrator| migrator.iter());
+            for migration in migrations.filter(|m| m.migration_type.is_up_migration()) {
+                let state = if applied.contains(&migration.version) { "applied" } else { "pending" };
+                println!("{:<8} {} {}", state, migration.version, migration.description);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn gen_keys() {
+    let key = || {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        hex::encode(bytes)
+    };
+    println!("JWT_SECRET={}", key());
+    println!("AUDIT_SIGNING_KEY={}", key());
+}
+
+async fn doctor() -> Result<(), CliError> {
+    let mut problems = 0;
+
+    let config = match Config::load() {
+        Ok(config) => {
+            println!("ok    configuration");
+            config
+        }
+        Err(e) => {
+            println!("FAIL  {}", e);
+            return Err(CliError::Unhealthy(e.problems.len()));
+        }
+    };
+
+    let db = match Database::connect(config.database_url.expose()).await {
+        Ok(db) => {
+            println!("ok    database connection");
+            db
+        }
+        Err(e) => {
+            println!("FAIL  database connection: {}", e);
+            return Err(CliError::Unhealthy(1));
+        }
+    };
+
+    match pending(&db).await {
+        Ok(pending) if pending.is_empty() => println!("ok    migrations"),
+        Ok(pending) => {
+            problems += 1;
+            println!("FAIL  {} pending migration(s): {}", pending.len(), pending.join(", "));
+        }
+        Err(e) => {
+            problems += 1;
+            println!("FAIL  migrations: {}", e);
+        }
+    }
+
+    if problems == 0 {
+        Ok(())
+    } else {
+        Err(CliError::Unhealthy(problems))
+    }
+}
@@ -1,154 +1,223 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use axum::body::Body;
-use axum::extract::FromRequestParts;
+use axum::extract::{ConnectInfo, FromRequestParts};
 use axum::http::request::Parts;
 use axum::http::StatusCode;
-use axum::response::{Html, Response};
-use tera::Tera;
+use axum::response::{Html, IntoResponse, Response};
+use ipnet::IpNet;
+use serde::Serialize;
 
+use crate::config;
+use crate::error::AppError;
+use crate::i18n::Locale;
+use crate::security::headers::CspNonce;
+use crate::templates::TemplateRegistry;
+use crate::utils::flash::Flash;
 use crate::utils::message::Message;
 
-pub type Templates = Arc<Tera>;
+/// The shared template registry, rendering in the language negotiated for
+/// the request by `i18n::localize` and showing its pending flash messages.
+#[derive(Clone)]
+pub struct Templates {
+    registry: Arc<TemplateRegistry>,
+    pub locale: Locale,
+    flash: Flash,
+    csp_nonce: CspNonce,
+}
 
-#[derive(Debug, Clone, Default)]
+impl Templates {
+    pub fn new(registry: Arc<TemplateRegistry>, locale: Locale, flash: Flash, csp_nonce: CspNonce) -> Self {
+        Templates { registry, locale, flash, csp_nonce }
+    }
+
+    pub fn render(&self, name: &str, context: &tera::Context) -> tera::Result<String> {
+        let mut context = context.clone();
+        let mut messages = self.flash.take();
+        if !messages.is_empty() {
+            if let Some(page_messages) = context.get("messages") {
+                messages.extend(serde_json::from_value::<Vec<Message>>(page_messages.clone())?);
+            }
+            context.insert("messages", &messages);
+        }
+        context.insert("lang", self.locale.code());
+        context.insert("csp_nonce", &self.csp_nonce.0);
+        let languages: Vec<(&str, String)> = Locale::all()
+            .map(|locale| (locale.code(), locale.t("language-name")))
+            .collect();
+        context.insert("languages", &languages);
+        self.registry.render(self.locale, name, &context)
+    }
+
+    /// Message `id` in the request's language.
+    pub fn t(&self, id: &str) -> String {
+        self.locale.t(id)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct AuthenticatedUser {
+    pub id: i32,
     pub email: String,
+    pub username: String,
+    pub is_admin: bool,
     pub purpose: String,
+    /// The admin behind the session when it is an impersonation session.
+    pub impersonator: Option<Impersonator>,
+    /// Whether the session came from an `Authorization: Bearer` header
+    /// rather than the `visit` cookie.
+    #[serde(skip)]
+    pub bearer: bool,
+    /// Scopes of the personal access token behind the session; `None` for
+    /// login sessions, which may do anything.
+    #[serde(skip)]
+    pub scopes: Option<Vec<String>>,
+    /// The language saved in the user's profile.
+    #[serde(skip)]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Impersonator {
+    pub id: i32,
+    pub email: String,
+    pub username: String,
+}
+
+impl AuthenticatedUser {
+    pub fn is_authenticated(&self) -> bool {
+        !self.email.is_empty()
+    }
+
+    pub fn is_impersonating(&self) -> bool {
+        self.impersonator.is_some()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|s| s == scope))
+    }
 }
 
+// the user is resolved once per request by `cookie_to_state`; anonymous
+// requests get the default (empty) user
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<AuthenticatedUser>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
 
-// #[async_trait]
-// impl FromRequestParts<AppState> for AuthenticatedUser
-// {
-//     type Rejection = Redirect;
-//
-//     async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
-//         let headers = HeaderMap::from_request_parts(parts, state)
-//             .await
-//             .map_err(|_| Redirect::to("/account/login"))?;
-//
-//         let cookie = headers
-//             .get(COOKIE)
-//             .and_then(|value| value.to_str().ok())
-//             .unwrap_or_default();
-//
-//         let token = match extract_cookie_value(cookie, "visit") {
-//             Some(token) => token,
-//             _ => return Err(Redirect::to("/account/login")),
-//         };
-//
-//         let claims = match decode_token(token).await {
-//             Ok(Some(claims)) => claims,
-//             _ => return Err(Redirect::to("/account/login")),
-//         };
-//         info!("{:?}",claims);
-//
-//         let user = details(state.db.clone(), claims.email.clone()).await;
-//
-//         match user {
-//             Ok(user) => {
-//                 let user = AuthenticatedUser {
-//                     email: user.email,
-//                     purpose: "".to_string(),
-//                 };
-//                 let mut state_user = state.user.lock().expect("mutex was poisoned");
-//                 *state_user = user.clone();
-//                 Ok(user)
-//             }
-//             _ => Err(Redirect::to("/account/login"))
-//         }
-//     }
-// }
-// an extractor that wraps another and measures how long time it takes to run
-#[derive(Debug)]
-pub struct Timing<E> {
-    pub extractor: E,
-    pub duration: Duration,
+/// Client details recorded alongside security events.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMeta {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
 }
 
-// we must implement both `FromRequestParts`
 #[async_trait]
-impl<S, T> FromRequestParts<S> for Timing<T>
+impl<S> FromRequestParts<S> for RequestMeta
 where
     S: Send + Sync,
-    T: FromRequestParts<S>,
 {
-    type Rejection = T::Rejection;
-
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let start = Instant::now();
-        let extractor = T::from_request_parts(parts, state).await?;
-        let duration = start.elapsed();
-        Ok(Timing {
-            extractor,
-            duration,
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let ip = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| {
+            let forwarded_for = header("x-forwarded-for");
+            client_ip(addr.ip(), forwarded_for.as_deref(), &config::get().trusted_proxies).to_string()
+        });
+        Ok(RequestMeta {
+            ip,
+            user_agent: header("user-agent"),
+            request_id: header("x-request-id"),
         })
     }
 }
 
-//
-// #[async_trait]
-// impl FromRequestParts<AppState> for AuthenticatedUser {
-//     type Rejection = ();
-//
-//     async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
-//         let headers = HeaderMap::from_request_parts(parts, state)
-//             .await
-//             .map_err(|_| ())?;
-//
-//         let cookie = headers
-//             .get(COOKIE)
-//             .and_then(|value| value.to_str().ok())
-//             .unwrap_or_default();
-//
-//         let token = match extract_cookie_value(cookie, "visit") {
-//             Some(token) => token,
-//             _ => return Err(()),
-//         };
-//
-//         let claims = match decode_token(token).await {
-//             Ok(Some(claims)) => claims,
-//             _ => return Err(()),
-//         };
-//         info!("{:?}", claims);
-//
-//         let user = details(state.db.clone(), claims.email.clone()).await;
-//
-//         match user {
-//             Ok(user) => {
-//                 let user = AuthenticatedUser {
-//                     email: user.email,
-//                     purpose: "".to_string(),
-//                 };
-//                 let mut state_user = state.user.lock().expect("mutex was poisoned");
-//                 *state_user = user.clone();
-//                 Ok(user)
-//             }
-//             _ => Err(()),
-//         }
-//     }
-// }
+/// Address of the client a connection from `peer` is made for.
+///
+/// X-Forwarded-For is only believed when `peer` is a trusted proxy, and is
+/// read from the right, past further trusted proxies: whatever is to the
+/// left of the first untrusted hop may have been made up by the client.
+pub fn client_ip(peer: IpAddr, forwarded_for: Option<&str>, trusted: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+    // listening on [::] reports IPv4 peers as mapped IPv6 addresses
+    let mut client = peer.to_canonical();
+    if !is_trusted(&client) {
+        return client;
+    }
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => client = ip.to_canonical(),
+            Err(_) => break,
+        }
+        if !is_trusted(&client) {
+            break;
+        }
+    }
+    client
+}
+
+/// Template context pre-filled with the values every page needs.
+pub fn page_context(user: &AuthenticatedUser) -> tera::Context {
+    let mut context = tera::Context::new();
+    if user.is_authenticated() {
+        context.insert("current_user", user);
+    }
+    context
+}
+
+/// Render a template, turning template failures into an `AppError`.
+pub fn render(templates: &Templates, name: &str, context: &tera::Context) -> Result<Html<String>, AppError> {
+    Ok(Html(templates.render(name, context)?))
+}
+
+/// The "check your email" page, shown whatever happened to the account.
+///
+/// Rendering takes the pending flash messages, so only call it on the way out.
+pub fn check_email_sent(templates: &Templates) -> Result<Response, AppError> {
+    Ok(render(templates, "email-verify", &tera::Context::new())?.into_response())
+}
 
+/// Re-render a form page with a message for the user.
+///
+/// Only for messages meant for the user; internal failures are returned as
+/// `AppError` so they are logged rather than shown.
 pub async fn html_err(
-    templates: &Arc<Tera>,
+    templates: &Templates,
     name: &str,
     context: &mut tera::Context,
     message: String,
-) -> Html<String> {
+) -> Result<Response, AppError> {
     // Insert the error message into the context
     context.insert("messages", &vec![Message {
         content: message.to_string(),
         tags: "danger".to_string(),
     }]);
 
-    // Render the template with the updated context
-    Html(templates.render(name, context).unwrap_or_else(|_| {
-        // Handle potential template rendering errors
-        "<h1>Error rendering template</h1>".to_string()
-    }))
+    Ok(render(templates, name, context)?.into_response())
 }
 
 pub fn build_redirect_with_cookie(token: &str, max_age:String, loc: &str) -> Response {
@@ -0,0 +1,70 @@
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+
+use crate::auth::models::ListUser;
+use crate::common::{page_context, render, AuthenticatedUser, Templates};
+use crate::directory::models::{Cursor, DirectoryEntry, DirectoryFilter, DirectoryPage, DirectoryQuery};
+use crate::error::AppError;
+use crate::state::AppState;
+
+const PAGE_LIMIT: i64 = 25;
+const AUTOCOMPLETE_LIMIT: i64 = 10;
+
+/// Only admins see emails, so only they may search by them.
+fn filter(query: &DirectoryQuery, user: &AuthenticatedUser) -> DirectoryFilter {
+    DirectoryFilter {
+        search: query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(str::to_string),
+        search_email: user.is_admin,
+        sort: query.sort,
+        // a cursor that does not parse starts over from the first page
+        after: query.after.as_deref().and_then(Cursor::decode),
+    }
+}
+
+/// Up to `limit` users and the cursor of the next page, if there is one.
+async fn page(state: &AppState, filter: &DirectoryFilter, limit: i64) -> Result<(Vec<ListUser>, Option<String>), AppError> {
+    let mut users = state.users.directory(filter, limit + 1).await?;
+    let next = if users.len() as i64 > limit {
+        users.truncate(limit as usize);
+        users.last().map(|user| Cursor::after(user).encode())
+    } else {
+        None
+    };
+    Ok((users, next))
+}
+
+fn entries(users: Vec<ListUser>, user: &AuthenticatedUser) -> Vec<DirectoryEntry> {
+    users.into_iter().map(|found| DirectoryEntry::new(found, user.is_admin)).collect()
+}
+
+pub async fn directory(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    Query(query): Query<DirectoryQuery>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
+
+    let (users, next) = page(&state, &filter(&query, &user), PAGE_LIMIT).await?;
+    context.insert("query", &query);
+    context.insert("users", &entries(users, &user));
+    context.insert("next", &next);
+    render(&templates, "directory", &context)
+}
+
+/// The directory as JSON, for autocomplete widgets.
+pub async fn autocomplete(
+    State(state): State<AppState>,
+    Query(query): Query<DirectoryQuery>,
+    user: AuthenticatedUser,
+) -> Result<Json<DirectoryPage>, AppError> {
+    let limit = query.limit.unwrap_or(AUTOCOMPLETE_LIMIT).clamp(1, PAGE_LIMIT);
+    let (users, next) = page(&state, &filter(&query, &user), limit).await?;
+    Ok(Json(DirectoryPage {
+        users: entries(users, &user),
+        next,
+    }))
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::models::ListUser;
+use crate::utils::date_config::date_format;
+
+/// Order of the member directory; users who joined at the same time go by id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectorySort {
+    #[default]
+    Newest,
+    Oldest,
+}
+
+impl DirectorySort {
+    /// The keyset comparison that continues after a cursor, and the SQL direction.
+    pub fn keyset(self) -> (&'static str, &'static str) {
+        match self {
+            DirectorySort::Newest => ("<", "DESC"),
+            DirectorySort::Oldest => (">", "ASC"),
+        }
+    }
+}
+
+/// The last row of a page, where the next page starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn after(user: &ListUser) -> Self {
+        Cursor { created_at: user.created_at, id: user.id }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}_{}", self.created_at.to_rfc3339_opts(SecondsFormat::Nanos, true), self.id)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let (created_at, id) = value.rsplit_once('_')?;
+        Some(Cursor {
+            created_at: DateTime::parse_from_rfc3339(created_at).ok()?.with_timezone(&Utc),
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// Query string of the directory page and its JSON variant.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DirectoryQuery {
+    pub q: Option<String>,
+    #[serde(default)]
+    pub sort: DirectorySort,
+    pub after: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Which users a directory page lists. Only verified, enabled users who
+/// have not hidden their profile are ever listed.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryFilter {
+    /// Matched against usernames, and against emails when `search_email` is set.
+    pub search: Option<String>,
+    pub search_email: bool,
+    pub sort: DirectorySort,
+    pub after: Option<Cursor>,
+}
+
+/// A user as listed in the directory; the email is only there for admins.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryEntry {
+    pub username: String,
+    pub img: Option<String>,
+    #[serde(with = "date_format")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl DirectoryEntry {
+    pub fn new(user: ListUser, with_email: bool) -> Self {
+        DirectoryEntry {
+            username: user.username,
+            img: user.img,
+            created_at: user.created_at,
+            email: with_email.then_some(user.email),
+        }
+    }
+}
+
+/// A page of the JSON directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryPage {
+    pub users: Vec<DirectoryEntry>,
+    /// Pass as `after` to get the next page; absent on the last one.
+    pub next: Option<String>,
+}
+
+/// A `LIKE` pattern matching `text` anywhere, its wildcards escaped with `\`.
+pub fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
@@ -0,0 +1,177 @@
+use axum::extract::{Request, State};
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::common::{page_context, AuthenticatedUser, Templates};
+use crate::i18n::Locale;
+use crate::security::headers::CspNonce;
+use crate::state::AppState;
+use crate::utils::db::QueryError;
+use crate::utils::flash::Flash;
+use crate::utils::jwt::DecodeTokenError;
+
+/// Every failure a handler can return.
+///
+/// The `Display` output is for logs only; users see `public_message_id`.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("database error: {0:?}")]
+    Query(#[from] QueryError),
+    #[error("token error: {0}")]
+    Token(#[from] DecodeTokenError),
+    #[error("password hashing error: {0}")]
+    Password(argon2::password_hash::Error),
+    #[error("template error: {0:?}")]
+    Template(#[from] tera::Error),
+    #[error("jwt encoding error: {0}")]
+    Jwt(String),
+    #[error("not found")]
+    NotFound,
+    #[error("forbidden")]
+    Forbidden,
+    #[error("action blocked while impersonating")]
+    Impersonating,
+}
+
+impl From<argon2::password_hash::Error> for AppError {
+    fn from(err: argon2::password_hash::Error) -> Self {
+        AppError::Password(err)
+    }
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Query(QueryError::RowNotFound | QueryError::NotFound) | AppError::NotFound => {
+                StatusCode::NOT_FOUND
+            }
+            AppError::Query(QueryError::PoolTimedOut | QueryError::ConnectionClosed) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            AppError::Query(QueryError::Duplicate { .. }) => StatusCode::CONFLICT,
+            AppError::Query(QueryError::ForeignKey { .. } | QueryError::CheckViolation { .. }) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Token(_) => StatusCode::BAD_REQUEST,
+            AppError::Forbidden | AppError::Impersonating => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Catalog id of the message shown to users.
+    pub fn public_message_id(&self) -> &'static str {
+        if let AppError::Impersonating = self {
+            return "error-impersonating";
+        }
+        match self.status() {
+            StatusCode::NOT_FOUND => "error-not-found",
+            StatusCode::SERVICE_UNAVAILABLE => "error-unavailable",
+            StatusCode::BAD_REQUEST => "error-bad-link",
+            StatusCode::CONFLICT => "error-conflict",
+            StatusCode::UNPROCESSABLE_ENTITY => "error-invalid-data",
+            StatusCode::FORBIDDEN => "error-forbidden",
+            _ => "error-internal",
+        }
+    }
+}
+
+/// What is left of an `AppError` once it has been logged; rendered into a
+/// page or JSON body by `render_errors`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: u16,
+    /// In the default language; error pages translate `message_id`.
+    pub message: String,
+    #[serde(skip)]
+    pub message_id: &'static str,
+    pub correlation_id: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let correlation_id = Uuid::new_v4().to_string();
+        if status.is_server_error() {
+            error!(correlation_id = %correlation_id, "{}", self);
+        } else {
+            warn!(correlation_id = %correlation_id, "{}", self);
+        }
+
+        let report = ErrorReport {
+            status,
+            code: status.as_u16(),
+            message: Locale::default().t(self.public_message_id()),
+            message_id: self.public_message_id(),
+            correlation_id,
+        };
+        // plain-text fallback for routes outside `render_errors`
+        let mut response = (
+            status,
+            format!("{} (reference: {})", report.message, report.correlation_id),
+        ).into_response();
+        response.extensions_mut().insert(report);
+        response
+    }
+}
+
+fn wants_json(request: &Request) -> bool {
+    let accept = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    request.uri().path().starts_with("/api")
+        || (accept.contains("application/json") && !accept.contains("text/html"))
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a ErrorReport,
+}
+
+/// Turn `ErrorReport`s produced by handlers into an error page, or a JSON
+/// body for API clients.
+pub async fn render_errors(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let json = wants_json(&request);
+    let user = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .cloned()
+        .unwrap_or_default();
+    let templates = request
+        .extensions()
+        .get::<Templates>()
+        .cloned()
+        .unwrap_or_else(|| Templates::new(state.templates.clone(), Locale::default(), Flash::default(), CspNonce::default()));
+
+    let response = next.run(request).await;
+    let mut report = match response.extensions().get::<ErrorReport>() {
+        Some(report) => report.clone(),
+        None => return response,
+    };
+
+    if json {
+        let body = serde_json::to_string(&ErrorBody { error: &report }).unwrap_or_default();
+        return (report.status, [(CONTENT_TYPE, "application/json")], body).into_response();
+    }
+
+    let name = if report.status == StatusCode::NOT_FOUND { "404" } else { "500" };
+    report.message = templates.t(report.message_id);
+    let mut context = page_context(&user);
+    context.insert("error", &report);
+    match templates.render(name, &context) {
+        Ok(html) => (report.status, Html(html)).into_response(),
+        Err(e) => {
+            error!("Error rendering error page: {:?}", e);
+            response
+        }
+    }
+}
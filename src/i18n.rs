@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use axum::extract::{Request, State};
+use axum::http::header::{ACCEPT_LANGUAGE, COOKIE};
+use axum::middleware::Next;
+use axum::response::Response;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{negotiate_languages, parse_accepted_languages, NegotiationStrategy};
+use once_cell::sync::Lazy;
+use tracing::warn;
+use unic_langid::LanguageIdentifier;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::common::{AuthenticatedUser, Templates};
+use crate::security::headers::CspNonce;
+use crate::state::AppState;
+use crate::utils::cookie::extract_cookie_value;
+use crate::utils::flash::Flash;
+
+/// Cookie holding the language picked with the language switcher.
+pub const LANG_COOKIE: &str = "lang";
+
+/// Every catalog under `locales/`; the first one is the fallback.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en/main.ftl")),
+    ("ru", include_str!("../locales/ru/main.ftl")),
+];
+
+struct Catalog {
+    langids: Vec<LanguageIdentifier>,
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+static CATALOG: Lazy<Catalog> = Lazy::new(|| {
+    let mut langids = Vec::new();
+    let mut bundles = Vec::new();
+    for (code, source) in CATALOGS {
+        let langid: LanguageIdentifier = code.parse().expect("invalid locale code");
+        let resource = FluentResource::try_new(source.to_string())
+            .unwrap_or_else(|(_, errors)| panic!("invalid catalog for {}: {:?}", code, errors));
+        let mut bundle = FluentBundle::new_concurrent(vec![langid.clone()]);
+        // the output goes into HTML, not into bidi-aware UI toolkits
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(resource)
+            .unwrap_or_else(|errors| panic!("duplicate messages in catalog {}: {:?}", code, errors));
+        langids.push(langid);
+        bundles.push(bundle);
+    }
+    Catalog { langids, bundles }
+});
+
+/// One of the languages there is a catalog for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Locale(usize);
+
+impl Locale {
+    pub fn all() -> impl Iterator<Item = Locale> {
+        (0..CATALOGS.len()).map(Locale)
+    }
+
+    /// The supported locale a language tag asks for, e.g. `ru` for `ru-RU`.
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let requested: LanguageIdentifier = tag.trim().parse().ok()?;
+        Self::best_match(&[requested])
+    }
+
+    fn best_match(requested: &[LanguageIdentifier]) -> Option<Locale> {
+        let langids = &CATALOG.langids;
+        let matched = negotiate_languages(requested, langids, None, NegotiationStrategy::Lookup);
+        let first = matched.first()?;
+        langids.iter().position(|langid| langid == *first).map(Locale)
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.0
+    }
+
+    pub fn code(&self) -> &'static str {
+        CATALOGS[self.0].0
+    }
+
+    /// Format message `id`, falling back to the default locale and, failing
+    /// that, to the id itself.
+    pub fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        for index in [self.0, 0] {
+            let bundle = &CATALOG.bundles[index];
+            if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+                let mut errors = Vec::new();
+                let text = bundle.format_pattern(pattern, args, &mut errors);
+                if !errors.is_empty() {
+                    warn!("Errors formatting {} for {}: {:?}", id, self.code(), errors);
+                }
+                return text.into_owned();
+            }
+        }
+        warn!("Missing translation {} for {}", id, self.code());
+        id.to_string()
+    }
+
+    pub fn t(&self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    /// A validator error in this language. Errors are keyed by their `code`;
+    /// the validator's `params` are passed on as message arguments.
+    pub fn validation_message(&self, field: &str, error: &ValidationError) -> String {
+        let mut args = FluentArgs::new();
+        args.set("field", field.to_string());
+        for (name, value) in &error.params {
+            if let Some(value) = fluent_value(value) {
+                args.set(name.to_string(), value);
+            }
+        }
+        let id = format!("validation-{}", error.code);
+        if CATALOG.bundles[0].has_message(&id) {
+            self.format(&id, Some(&args))
+        } else {
+            self.format("validation-invalid", Some(&args))
+        }
+    }
+
+    /// One message per invalid field, ordered by field name.
+    pub fn validation_messages(&self, errors: &ValidationErrors) -> Vec<(String, Vec<String>)> {
+        let mut fields: Vec<_> = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors.iter().map(|e| self.validation_message(field, e)).collect();
+                (field.to_string(), messages)
+            })
+            .collect();
+        fields.sort();
+        fields
+    }
+}
+
+fn fluent_value(value: &serde_json::Value) -> Option<FluentValue<'static>> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64().map(FluentValue::from),
+        serde_json::Value::String(string) => Some(FluentValue::from(string.clone())),
+        _ => None,
+    }
+}
+
+/// Pick the language for a request: the user's saved preference, then the
+/// language switcher cookie, then `Accept-Language`.
+pub fn negotiate(preference: Option<&str>, cookie: Option<&str>, accept_language: Option<&str>) -> Locale {
+    preference
+        .and_then(Locale::parse)
+        .or_else(|| cookie.and_then(Locale::parse))
+        .or_else(|| accept_language.and_then(|header| Locale::best_match(&parse_accepted_languages(header))))
+        .unwrap_or_default()
+}
+
+/// The Tera `t()` function of one locale: `{{ t(key="login-title") }}`.
+/// Arguments other than `key` are passed to the message.
+pub fn tera_function(locale: Locale) -> impl tera::Function {
+    move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+        let key = match args.get("key").and_then(|key| key.as_str()) {
+            Some(key) => key,
+            None => return Err(tera::Error::msg("t() needs a `key` argument")),
+        };
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args.iter().filter(|(name, _)| *name != "key") {
+            match fluent_value(value) {
+                Some(value) => fluent_args.set(name.to_string(), value),
+                None => fluent_args.set(name.to_string(), value.to_string()),
+            }
+        }
+        Ok(tera::Value::String(locale.format(key, Some(&fluent_args))))
+    }
+}
+
+/// Negotiate the language of the request and hand handlers a `Templates`
+/// that renders in it. Runs after `cookie_to_state`, `security_headers` and
+/// `carry_flash`.
+pub async fn localize(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let cookie = headers
+        .get(COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|cookies| extract_cookie_value(cookies, LANG_COOKIE));
+    let accept_language = headers.get(ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok());
+    // an admin impersonating someone keeps their own language
+    let preference = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .filter(|user| !user.is_impersonating())
+        .and_then(|user| user.locale.clone());
+
+    let locale = negotiate(preference.as_deref(), cookie.as_deref(), accept_language);
+    request.extensions_mut().insert(locale);
+    let flash = request.extensions().get::<Flash>().cloned().unwrap_or_default();
+    let nonce = request.extensions().get::<CspNonce>().cloned().unwrap_or_default();
+    request.extensions_mut().insert(Templates::new(state.templates.clone(), locale, flash, nonce));
+    next.run(request).await
+}
@@ -1,32 +1,81 @@
+pub mod assets;
+pub mod cli;
 pub mod common;
+pub mod config;
+pub mod error;
+pub mod i18n;
+pub mod metrics;
 
 pub mod routes_assets;
 
 pub mod routes_account;
+pub mod routes_admin;
+pub mod routes_api;
+pub mod routes_health;
 pub mod routes_index;
+pub mod routes_security;
+pub mod routes_users;
 
 pub mod state;
+pub mod telemetry;
+pub mod templates;
+pub mod tls;
 
 pub mod utils {
     pub mod date_config;
     pub mod q_body;
     pub mod jwt;
+    pub mod mail;
     pub mod message;
+    pub mod flash;
     pub mod date_option;
     pub mod db;
+    pub mod migrate;
     pub mod cookie;
 }
 pub mod auth {
     pub mod handlers;
     pub mod models;
-    // pub mod repository;
+    pub mod repository;
     pub mod middleware;
-    // pub mod views;
+}
+pub mod api {
+    pub mod docs;
+    pub mod handlers;
+    pub mod models;
+}
+pub mod audit {
+    pub mod chain;
+    pub mod handlers;
+    pub mod log;
+    pub mod models;
+    pub mod repository;
+}
+pub mod storage {
+    pub mod database;
+    pub mod memory;
+    pub mod postgres;
+    pub mod sqlite;
+}
+pub mod security {
+    pub mod handlers;
+    pub mod headers;
+}
+pub mod admin {
+    pub mod handlers;
+}
+pub mod directory {
+    pub mod handlers;
+    pub mod models;
 }
 pub mod profile {
     pub mod handlers;
     pub mod models;
-    // pub mod repository;
-    // pub mod views;
 }
 
+pub mod tokens {
+    pub mod handlers;
+    pub mod models;
+    pub mod repository;
+    pub mod secret;
+}
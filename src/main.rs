@@ -1,60 +1,156 @@
 use std::borrow::Cow;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use axum::Router;
 use axum::error_handling::HandleErrorLayer;
 use axum::http::StatusCode;
-use axum::middleware::from_fn_with_state;
+use axum::middleware::{from_fn, from_fn_with_state};
 use axum::response::IntoResponse;
+use axum_server::Handle;
+use clap::Parser;
 use dotenv::dotenv;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tower::{BoxError, ServiceBuilder};
-use tower_http::trace::TraceLayer;
-use tracing::{error, info};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{error, info, Level};
 
 use axum_example::auth::middleware::cookie_to_state;
+use axum_example::cli::{self, Cli, Command};
+use axum_example::config::{self, Config, LogFormat};
+use axum_example::error::{render_errors, AppError};
+use axum_example::i18n::localize;
+use axum_example::metrics::{self, track_requests};
 use axum_example::routes_account;
+use axum_example::routes_admin;
+use axum_example::routes_api;
 use axum_example::routes_assets;
+use axum_example::routes_health;
 use axum_example::routes_index;
+use axum_example::routes_security;
+use axum_example::routes_users;
+use axum_example::security::headers::security_headers;
 use axum_example::state::AppState;
+use axum_example::telemetry::{self, request_span};
+use axum_example::tls;
+use axum_example::utils::flash::carry_flash;
+use axum_example::utils::jwt::ar_dummy_verify;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    tracing_subscriber::fmt::init();
 
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve().await,
+        command => {
+            let _telemetry = telemetry::init(LogFormat::Pretty, None);
+            if let Err(err) = cli::run(command).await {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+async fn serve() {
+    let config = match Config::load() {
+        Ok(config) => config::init(config),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let _telemetry = telemetry::init(config.log_format, config.otlp_endpoint.as_deref());
+    info!("Configuration:\n{}", config.summary());
 
-    let state = match AppState::new().await {
+    let state = match AppState::new(config.clone()).await {
         Ok(state) => state,
         Err(err) => {
-            error!("Failed to create app state: {:?}", err);
-            return;
+            error!("Failed to create app state: {}", err);
+            std::process::exit(1);
         }
     };
 
+    // build the dummy hash used for unknown logins now rather than on the
+    // first miss, where it would make that response measurably slower
+    ar_dummy_verify("");
+
     let assets_router = routes_assets::build_routes();
+    let health_router = routes_health::build_routes(state.clone());
+    let security_router = routes_security::build_routes();
 
     let index_router = routes_index::build_routes(state.clone());
     let account_router = routes_account::build_routes(state.clone());
+    let admin_router = routes_admin::build_routes(state.clone());
+    let users_router = routes_users::build_routes(state.clone());
+    let api_router = routes_api::build_routes(state.clone());
 
     let app = Router::new()
         .merge(assets_router)
+        .merge(health_router)
+        .merge(security_router)
         .merge(index_router)
         .merge(account_router)
+        .merge(admin_router)
+        .merge(users_router)
+        .merge(api_router)
+        .fallback(handler_404)
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(handle_error))
                 .load_shed()
                 .concurrency_limit(1024)
                 .timeout(Duration::from_secs(10))
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                .layer(from_fn(track_requests))
                 .layer(from_fn_with_state(state.clone(), cookie_to_state))
-                .layer(TraceLayer::new_for_http())
-        )
-        .fallback(handler_404);
+                .layer(from_fn_with_state(state.clone(), security_headers))
+                .layer(from_fn(carry_flash))
+                .layer(from_fn_with_state(state.clone(), localize))
+                .layer(from_fn_with_state(state.clone(), render_errors)),
+        );
+
+    let addr = config.bind_addr;
+    if let Some(files) = &config.tls {
+        let tls = match tls::load(files).await {
+            Ok(tls) => tls,
+            Err(err) => {
+                error!("Failed to load TLS certificate: {}", err);
+                return;
+            }
+        };
+        tls::watch(tls.clone(), files.clone());
+        if let Some(redirect_addr) = config.http_redirect_addr {
+            tokio::spawn(serve_redirect(redirect_addr, addr.port()));
+        }
+
+        let handle = Handle::new();
+        let shutdown = handle.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown.graceful_shutdown(Some(Duration::from_secs(10)));
+        });
+
+        info!("Listening on https://{}", addr);
+        if let Err(err) = axum_server::bind_rustls(addr, tls)
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+        {
+            error!("Server error: {:?}", err);
+        }
+        return;
+    }
 
-    let addr = SocketAddr::from((Ipv4Addr::new(127, 0, 0, 1), 8000));
     let listener = match TcpListener::bind(&addr).await {
         Ok(listener) => listener,
         Err(err) => {
@@ -65,7 +161,7 @@ async fn main() {
 
     info!("Listening on {}", addr);
 
-    if let Err(err) = axum::serve(listener, app.into_make_service())
+    if let Err(err) = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
     {
@@ -73,6 +169,24 @@ async fn main() {
     }
 }
 
+/// Plain-HTTP listener that only redirects to the HTTPS one.
+async fn serve_redirect(addr: SocketAddr, https_port: u16) {
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Failed to bind redirect listener to {}: {:?}", addr, err);
+            return;
+        }
+    };
+    info!("Redirecting http://{} to HTTPS", addr);
+    if let Err(err) = axum::serve(listener, tls::redirect_router(https_port))
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+    {
+        error!("Redirect listener error: {:?}", err);
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -88,21 +202,26 @@ async fn shutdown_signal() {
             .await;
     };
 
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
     tokio::select! {
         _ = ctrl_c => {},
         _ = terminate => {},
     }
 }
 
-async fn handler_404() -> impl IntoResponse {
-    (StatusCode::NOT_FOUND, "404")
+async fn handler_404() -> AppError {
+    AppError::NotFound
 }
 
 async fn handle_error(error: BoxError) -> impl IntoResponse {
     if error.is::<tower::timeout::error::Elapsed>() {
+        metrics::request_rejected("timeout");
         return (StatusCode::REQUEST_TIMEOUT, Cow::from("request timed out"));
     }
     if error.is::<tower::load_shed::error::Overloaded>() {
+        metrics::request_rejected("load_shed");
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             Cow::from("service is overloaded, try again later"),
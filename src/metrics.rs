@@ -0,0 +1,126 @@
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder, HistogramVec,
+    IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use tracing::Span;
+
+use crate::storage::database::Database;
+
+static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "HTTP requests handled, by route and status",
+        &["method", "route", "status"]
+    )
+    .expect("metric registers once")
+});
+
+static HTTP_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time to produce a response, by route",
+        &["method", "route"]
+    )
+    .expect("metric registers once")
+});
+
+static HTTP_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_requests_rejected_total",
+        "Requests dropped by load shedding or the request timeout",
+        &["reason"]
+    )
+    .expect("metric registers once")
+});
+
+static DB_POOL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "db_pool_connections",
+        "Database pool connections, by state",
+        &["state"]
+    )
+    .expect("metric registers once")
+});
+
+static PASSWORD_HASHING: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "password_hashing_seconds",
+        "Time spent in Argon2, by operation",
+        &["operation"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    )
+    .expect("metric registers once")
+});
+
+static LOGINS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "logins_total",
+        "Login attempts, by channel and outcome",
+        &["channel", "outcome"]
+    )
+    .expect("metric registers once")
+});
+
+/// Count and time every request under the route pattern it matched, so
+/// that ids in paths do not create new series. The pattern is also
+/// recorded on the request span.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+    Span::current().record("route", route.as_str());
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    HTTP_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    HTTP_REQUESTS
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
+
+/// `reason` is `load_shed` or `timeout`.
+pub fn request_rejected(reason: &str) {
+    HTTP_REJECTED.with_label_values(&[reason]).inc();
+}
+
+/// Time an Argon2 `operation` (`hash` or `verify`).
+pub fn time_password_hashing<T>(operation: &str, f: impl FnOnce() -> T) -> T {
+    let _timer = PASSWORD_HASHING.with_label_values(&[operation]).start_timer();
+    f()
+}
+
+/// `channel` is `web` or `api`.
+pub fn login_attempt(channel: &str, success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    LOGINS.with_label_values(&[channel, outcome]).inc();
+}
+
+/// Every metric in the Prometheus text format, with the pool gauges read
+/// at scrape time.
+pub fn render(db: &Database) -> String {
+    if let Some(stats) = db.pool_stats() {
+        let idle = stats.idle as i64;
+        DB_POOL.with_label_values(&["idle"]).set(idle);
+        DB_POOL.with_label_values(&["in_use"]).set(stats.size as i64 - idle);
+        DB_POOL.with_label_values(&["max"]).set(stats.max as i64);
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Error encoding metrics: {:?}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
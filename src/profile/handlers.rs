@@ -3,199 +3,284 @@ use std::collections::HashMap;
 use axum::{
     Extension,
     extract::State,
-    response::{Html, IntoResponse},
-    extract::Query,
+    response::{IntoResponse, Response},
+    extract::{Path, Query},
     response::Redirect,
 };
+use axum::http::header::REFERER;
+use axum::http::{HeaderMap, Uri};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use axum_extra::extract::Form;
 use chrono::Utc;
 use tera::Context;
 use validator::Validate;
 
-use crate::auth::models::User;
-use crate::common::html_err;
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::auth::models::{Claims, ListUser};
+use crate::common::{build_redirect_with_cookie, check_email_sent, html_err, page_context, render, AuthenticatedUser, RequestMeta};
 use crate::common::Templates;
-use crate::profile::models::{FormPasswordChange, FormVerifyEmail, PasswordChange, UpdateUserEmailVerify};
+use crate::error::AppError;
+use crate::i18n::{Locale, LANG_COOKIE};
+use crate::profile::models::{
+    FormLanguage, FormPasswordChange, FormPasswordReset, FormPublicProfile, FormVerifyEmail, PasswordChange,
+    PublicProfile, UpdateUserEmailVerify, Visibility,
+};
 use crate::state::AppState;
-use crate::utils::db::{check_email, get_user, query_update_password, query_update_user};
-use crate::utils::jwt::{ar_hash_password, decode_token, encode_jwt};
+use crate::utils::db::QueryError;
+use crate::utils::flash::Flash;
+use crate::utils::date_option::get_max_age_seconds;
+use crate::utils::jwt::{ar_hash_password, decode_token, encode_jwt, encode_session_after};
+use crate::utils::mail;
 use crate::utils::message::handle_errors;
 
+/// Claims of the `token` query parameter, if it is valid and issued for `purpose`.
+async fn query_token(params: &HashMap<String, String>, purpose: &str) -> Option<Claims> {
+    let token = params.get("token")?.clone();
+    decode_token(token)
+        .await
+        .ok()
+        .flatten()
+        .filter(|claims| claims.purpose == purpose)
+}
+
 pub async fn user(
     State(state): State<AppState>,
     Extension(templates): Extension<Templates>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    let mut context = Context::new();
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
 
-    let user = state.user.lock().map(|user| user.clone()).unwrap();
+    let found = ListUser::from(state.users.find_by_email(&user.email).await?);
+    context.insert("user", &found);
+    render(&templates, "detail", &context)
+}
 
-    match get_user(&state.db, user.email.clone()).await {
-        Ok(row) => {
-            context.insert("user", &User::from_row(&row));
-            Ok(Html(templates.render("detail", &context).unwrap()).into_response())
-        }
-        Err(e) => Err(html_err(
-            &templates,
-            "detail",
-            &mut context,
-            format!("Error retrieving user: {:?}", e),
-        ).await.into_response()),
+/// A user's public page. Missing, disabled and hidden accounts, and
+/// members-only ones for guests, all get the same 404.
+pub async fn get_public_profile(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let found = match state.users.find_by_username(&username).await {
+        Ok(found) if !found.is_disabled() && found.visibility.allows(found.id, &user) => found,
+        Ok(_) | Err(QueryError::RowNotFound) => return Err(AppError::NotFound),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut context = page_context(&user);
+    context.insert("is_own", &(user.is_authenticated() && found.id == user.id));
+    context.insert("profile", &PublicProfile::from(ListUser::from(found)));
+    render(&templates, "public_profile", &context)
+}
+
+async fn public_profile_context(state: &AppState, user: &AuthenticatedUser) -> Result<Context, AppError> {
+    let mut context = page_context(user);
+    context.insert("user", &ListUser::from(state.users.find_by_id(user.id).await?));
+    context.insert("visibilities", &Visibility::ALL);
+    Ok(context)
+}
+
+pub async fn get_public_profile_settings(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let context = public_profile_context(&state, &user).await?;
+    render(&templates, "public_profile_settings", &context)
+}
+
+pub async fn post_public_profile_settings(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+    Form(form): Form<FormPublicProfile>,
+) -> Result<Response, AppError> {
+    if let Err(errors) = form.validate() {
+        let mut context = public_profile_context(&state, &user).await?;
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "public_profile_settings", &context)?.into_response());
     }
+
+    let bio = Some(form.bio.trim()).filter(|bio| !bio.is_empty());
+    state.users.set_public_profile(user.id, bio, form.visibility).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("profile.update", &meta)
+        .actor(user.impersonator.as_ref().map_or(user.id, |impersonator| impersonator.id))
+        .subject_id(user.id)
+        .subject(&user.email)
+        .details(&format!("public profile, visibility {}", form.visibility.code()))).await;
+    flash.success(templates.t("flash-public-profile-saved"));
+    Ok(Redirect::to("/account/public-profile").into_response())
 }
 
 pub async fn get_verify_email(
     State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    meta: RequestMeta,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    let q_token = match params.get("token") {
-        Some(token) => token.clone(),
-        None => return Err(Redirect::to("/account/email-verify-resend").into_response()),
-    };
-
-    let user = match decode_token(q_token).await {
-        Ok(claims) => claims.unwrap(),
-        Err(_) => return Err(Redirect::to("/account/email-verify-resend").into_response()),
+) -> Result<Response, AppError> {
+    let user = match query_token(&params, "email-verify").await {
+        Some(claims) => claims,
+        None => match query_token(&params, "email-change").await {
+            Some(Claims { user_id: Some(user_id), email, .. }) => {
+                return change_email(&state, &templates, &flash, &meta, user_id, &email).await;
+            }
+            _ => {
+                flash.error(templates.t("error-bad-link"));
+                return Ok(Redirect::to("/account/email-verify-resend").into_response());
+            }
+        },
     };
 
-    if user.purpose != "email-verify" {
-        return Err(Redirect::to("/account/email-verify-resend").into_response());
-    }
-
     let update = UpdateUserEmailVerify {
-        email: user.email,
+        email: user.email.clone(),
         is_verify: true,
         updated_at: Some(Utc::now()),
     };
 
-    match query_update_user(&state.db, update).await {
-        Ok(_) => Ok(Redirect::to("/account/login").into_response()),
-        Err(_) => Err(Redirect::to("/account/email-verify-resend").into_response()),
+    state.users.set_verified(update).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("email.verify", &meta).subject(&user.email)).await;
+    flash.success(templates.t("flash-email-verified"));
+    Ok(Redirect::to("/account/login").into_response())
+}
+
+/// Move an account to the address an email change link was sent to. The
+/// sessions, bound to the old address, end with it.
+async fn change_email(
+    state: &AppState,
+    templates: &Templates,
+    flash: &Flash,
+    meta: &RequestMeta,
+    user_id: i32,
+    email: &str,
+) -> Result<Response, AppError> {
+    if let Err(e) = state.users.change_email(user_id, email).await {
+        if e.user_field() != Some("email") {
+            return Err(e.into());
+        }
+        // taken since the link was sent; only the mailbox owner sees this
+        flash.error(templates.t("error-email-change-taken"));
+        return Ok(Redirect::to("/account/login").into_response());
     }
+    record(state.audit.as_ref(), NewAuditEvent::new("email.change", meta)
+        .subject_id(user_id)
+        .subject(email)).await;
+    flash.success(templates.t("flash-email-changed"));
+    Ok(Redirect::to("/account/login").into_response())
 }
 
 pub async fn get_verify_email_resend(
     Extension(templates): Extension<Templates>,
-) -> impl IntoResponse {
-    Html(templates.render("email-verify-resend", &Context::new()).unwrap()).into_response()
+) -> Result<impl IntoResponse, AppError> {
+    render(&templates, "email-verify-resend", &Context::new())
 }
 
 pub async fn post_verify_email_resend(
     State(state): State<AppState>,
     Extension(templates): Extension<Templates>,
+    meta: RequestMeta,
     Form(form): Form<FormVerifyEmail>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
     if let Err(errors) = form.validate() {
-        context.insert("messages", &handle_errors(errors).await);
-        return Err(Html(templates.render("email-verify-resend", &context).unwrap()).into_response());
-    }
-
-    if let Ok(false) = check_email(&state.db, form.email.clone()).await {
-        return Err(html_err(
-            &templates,
-            "email-verify-resend",
-            &mut context,
-            format!("Email not found: {:?}", form.email),
-        ).await.into_response());
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "email-verify-resend", &context)?.into_response());
     }
 
-    match encode_jwt(form.email.clone(), "email-verify".to_string(), 1).await {
-        Ok(token) => {
-            context.insert("verify", &token);
-            Ok(Html(templates.render("email-verify", &context).unwrap()).into_response())
+    // the response is the same whether or not the account exists
+    let user = match state.users.find_by_email(&form.email).await {
+        Ok(user) => user,
+        Err(_) => {
+            record(state.audit.as_ref(), NewAuditEvent::new("email.verify_resend", &meta)
+                .subject(&form.email)
+                .details("unknown email")).await;
+            return check_email_sent(&templates);
         }
-        Err(e) => Err(html_err(
-            &templates,
-            "email-verify-resend",
-            &mut context,
-            format!("Error encoding JWT: {:?}", e),
-        ).await.into_response()),
+    };
+
+    if user.is_verify {
+        record(state.audit.as_ref(), NewAuditEvent::new("email.verify_resend", &meta)
+            .subject_id(user.id)
+            .subject(&user.email)
+            .details("already verified")).await;
+        return check_email_sent(&templates);
     }
+
+    let token = encode_jwt(user.email.clone(), "email-verify".to_string(), 1)
+        .await
+        .map_err(AppError::Jwt)?;
+    record(state.audit.as_ref(), NewAuditEvent::new("email.verify_resend", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    mail::send(mail::verify_email(&user.email, &token));
+    check_email_sent(&templates)
 }
 
 pub async fn get_password_reset(
     Extension(templates): Extension<Templates>,
-) -> impl IntoResponse {
-    Html(templates.render("reset-password", &Context::new()).unwrap()).into_response()
+) -> Result<impl IntoResponse, AppError> {
+    render(&templates, "reset-password", &Context::new())
 }
 
 pub async fn post_password_reset(
     State(state): State<AppState>,
     Extension(templates): Extension<Templates>,
-    Form(form): Form<FormVerifyEmail>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+    meta: RequestMeta,
+    Form(form): Form<FormPasswordReset>,
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
     if let Err(errors) = form.validate() {
-        context.insert("messages", &handle_errors(errors).await);
-        return Err(Html(templates.render("reset-password", &context).unwrap()).into_response());
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "reset-password", &context)?.into_response());
     }
 
-    if let Ok(false) = check_email(&state.db, form.email.clone()).await {
-        return Err(html_err(
-            &templates,
-            "reset-password",
-            &mut context,
-            format!("Email not found: {:?}", form.email),
-        ).await.into_response());
-    }
-
-    let user = match get_user(&state.db, form.email.clone()).await {
-        Ok(row) => User::from_row(&row),
-        Err(e) => return Err(html_err(
-            &templates,
-            "reset-password",
-            &mut context,
-            format!("Error retrieving user: {:?}", e),
-        ).await.into_response()),
+    // the response is the same whether or not the account exists
+    let user = match state.users.find_by_login(&form.login).await {
+        Ok(user) => user,
+        Err(_) => {
+            record(state.audit.as_ref(), NewAuditEvent::new("password_reset.request", &meta)
+                .subject(&form.login)
+                .details("unknown user")).await;
+            return check_email_sent(&templates);
+        }
     };
 
-    match encode_jwt(user.email.clone(), "reset-password".to_string(), 1).await {
-        Ok(token) => {
-            context.insert("pwd", &token);
-            Ok(Html(templates.render("email-verify", &context).unwrap()).into_response())
-        }
-        Err(e) => Err(html_err(
-            &templates,
-            "reset-password",
-            &mut context,
-            format!("Error encoding JWT: {:?}", e),
-        ).await.into_response()),
-    }
+    let token = encode_jwt(user.email.clone(), "reset-password".to_string(), 1)
+        .await
+        .map_err(AppError::Jwt)?;
+    record(state.audit.as_ref(), NewAuditEvent::new("password_reset.request", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    mail::send(mail::reset_password(&user.email, &token));
+    check_email_sent(&templates)
 }
 
 pub async fn get_reset_password_confirm(
     Query(params): Query<HashMap<String, String>>,
     Extension(templates): Extension<Templates>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
-    let q_token = match params.get("token") {
-        Some(token) => token.clone(),
-        None => return Err(Redirect::to("/account/reset-password").into_response()),
-    };
-
-    let user = match decode_token(q_token).await {
-        Ok(claims) => claims.unwrap(),
-        Err(_) => return Err(Redirect::to("/account/reset-password").into_response()),
-    };
+    if !params.contains_key("token") {
+        return Ok(Redirect::to("/account/reset-password").into_response());
+    }
 
-    if user.purpose == "reset-password" {
-        Ok(Html(
-            templates
-                .render("reset-password-confirm", &Context::new())
-                .unwrap()
-        ).into_response())
+    if query_token(&params, "reset-password").await.is_some() {
+        Ok(render(&templates, "reset-password-confirm", &context)?.into_response())
     } else {
-        let error_html = html_err(
+        html_err(
             &templates,
             "reset-password-confirm",
             &mut context,
-            "Token is not for password reset!".to_string(),
-        ).await;
-
-        Err(error_html.into_response())
+            templates.t("error-bad-link"),
+        ).await
     }
 }
 
@@ -203,62 +288,144 @@ pub async fn post_reset_password_confirm(
     State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
     Extension(templates): Extension<Templates>,
+    flash: Flash,
+    meta: RequestMeta,
     Form(form): Form<FormPasswordChange>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<Response, AppError> {
     let mut context = Context::new();
 
     if let Err(errors) = form.validate() {
-        context.insert("messages", &handle_errors(errors).await);
-        return Err(Html(templates.render("reset-password-confirm", &context).unwrap()).into_response());
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "reset-password-confirm", &context)?.into_response());
     }
 
-    let q_token = match params.get("token") {
-        Some(token) => token.clone(),
-        None => return Err(Redirect::to("/account/reset-password").into_response()),
+    let user = match query_token(&params, "reset-password").await {
+        Some(claims) => claims,
+        None => {
+            flash.error(templates.t("error-bad-link"));
+            return Ok(Redirect::to("/account/reset-password").into_response());
+        }
     };
 
-    let user = match decode_token(q_token).await {
-        Ok(claims) => claims.unwrap(),
-        Err(_) => return Err(Redirect::to("/account/reset-password").into_response()),
+    let user = state.users.find_by_email(&user.email).await?;
+    let hashed_password = ar_hash_password(&form.password)?;
+
+    let password_change = PasswordChange {
+        email: user.email.clone(),
+        password: hashed_password,
+        updated_at: Some(Utc::now()),
     };
 
-    if user.purpose != "reset-password" {
-        return Err(Redirect::to("/account/reset-password").into_response());
-    }
+    state.users.update_password(password_change).await?;
+    // whoever reset the password may be locking someone else out
+    state.sessions.revoke_all(&user.email).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("password_reset.complete", &meta)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    flash.success(templates.t("flash-password-reset"));
+    Ok(Redirect::to("/account/login").into_response())
+}
 
-    let user = match get_user(&state.db, user.email.clone()).await {
-        Ok(row) => User::from_row(&row),
-        Err(e) => return Err(html_err(
-            &templates,
-            "reset-password-confirm",
-            &mut context,
-            format!("Error retrieving user: {:?}", e),
-        ).await.into_response()),
-    };
+pub async fn get_password_change(
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
+    context.insert("user", &user);
+    render(&templates, "password_change", &context)
+}
 
-    let hashed_password = match ar_hash_password(&form.password) {
-        Ok(hashed) => hashed,
-        Err(e) => return Err(html_err(
-            &templates,
-            "reset-password-confirm",
-            &mut context,
-            format!("Error hashing password: {:?}", e),
-        ).await.into_response()),
-    };
+pub async fn post_password_change(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+    Form(form): Form<FormPasswordChange>,
+) -> Result<Response, AppError> {
+    let mut context = page_context(&user);
+    context.insert("user", &user);
+
+    if let Err(errors) = form.validate() {
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "password_change", &context)?.into_response());
+    }
+
+    let hashed_password = ar_hash_password(&form.password)?;
 
     let password_change = PasswordChange {
-        email: user.email,
+        email: user.email.clone(),
         password: hashed_password,
         updated_at: Some(Utc::now()),
     };
 
-    match query_update_password(&state.db, password_change).await {
-        Ok(_) => Ok(Redirect::to("/account/login").into_response()),
-        Err(e) => Err(html_err(
-            &templates,
-            "reset-password-confirm",
-            &mut context,
-            format!("Error updating password: {:?}", e),
-        ).await.into_response()),
+    state.users.update_password(password_change).await?;
+    // end every other session; this one goes on with a token issued after the cutoff
+    state.sessions.revoke_all(&user.email).await?;
+    let revoked_at = state.users.find_by_email(&user.email).await?.sessions_revoked_at.unwrap_or_else(Utc::now);
+    let token = encode_session_after(user.email.clone(), revoked_at)
+        .await
+        .map_err(AppError::Jwt)?;
+    record(state.audit.as_ref(), NewAuditEvent::new("password.change", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    flash.success(templates.t("flash-password-changed"));
+    Ok(build_redirect_with_cookie(&token, get_max_age_seconds(), "/account/detail"))
+}
+
+pub async fn get_delete_user(
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let mut context = page_context(&user);
+    context.insert("user", &user);
+    render(&templates, "delete-user", &context)
+}
+
+pub async fn post_delete_user(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<impl IntoResponse, AppError> {
+    state.users.delete(&user.email).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("account.delete", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)).await;
+    flash.info(templates.t("flash-account-deleted"));
+    Ok(build_redirect_with_cookie("", "0".to_string(), "/"))
+}
+
+/// Switch the interface language, remembering it in a cookie and, for a
+/// signed-in user, in their profile.
+pub async fn post_language(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(form): Form<FormLanguage>,
+) -> Result<Response, AppError> {
+    let locale = Locale::parse(&form.lang).unwrap_or_default();
+    if user.is_authenticated() && !user.is_impersonating() {
+        state.users.set_locale(user.id, locale.code()).await?;
     }
+
+    // back to the page the switcher was on, keeping only the path so the
+    // redirect cannot leave the site
+    let back = headers
+        .get(REFERER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|referer| referer.parse::<Uri>().ok())
+        .and_then(|uri| uri.path_and_query().map(|path| path.to_string()))
+        .filter(|path| path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\"))
+        .unwrap_or_else(|| "/".to_string());
+
+    let cookie = Cookie::build((LANG_COOKIE, locale.code()))
+        .path("/")
+        .max_age(cookie::time::Duration::days(365))
+        .same_site(SameSite::Lax);
+    Ok((jar.add(cookie), Redirect::to(&back)).into_response())
 }
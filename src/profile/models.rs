@@ -2,8 +2,11 @@ use axum::{body::Body, http::Response};
 use chrono::serde::ts_seconds_option;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator_derive::Validate;
 
+use crate::auth::models::ListUser;
+use crate::common::AuthenticatedUser;
 use crate::utils::date_config::date_format;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -25,18 +28,6 @@ pub struct NewUser {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ListUser {
-    pub id: i32,
-    pub email: String,
-    pub username: String,
-    pub img: Option<String>,
-    #[serde(with = "date_format")]
-    pub created_at: DateTime<Utc>,
-    #[serde(with = "ts_seconds_option")]
-    pub updated_at: Option<DateTime<Utc>>,
-}
-
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FormNewUser {
     pub email: String,
@@ -51,9 +42,19 @@ pub struct UpdateUser {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Validate, Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct FormUpdateUser {
+    #[validate(email(code = "email"))]
+    #[schema(format = "email")]
     pub email: String,
+    #[validate(length(
+        min = 3,
+        max = 20,
+        code = "username-length"
+    ))]
+    // logins containing '@' are looked up as emails too
+    #[validate(does_not_contain(pattern = "@", code = "username-at"))]
+    #[schema(min_length = 3, max_length = 20)]
     pub username: String,
 }
 
@@ -67,7 +68,7 @@ pub struct PasswordChange {
 
 #[derive(Validate, Debug, Clone, Deserialize, Serialize)]
 pub struct FormPasswordChange {
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    #[validate(length(min = 8, code = "password-length"))]
     pub password: String,
 }
 
@@ -76,19 +77,24 @@ pub enum EnumError {
     ErrString(String),
 }
 
-#[derive(Validate, Debug, Clone, Deserialize, Serialize)]
+#[derive(Validate, Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct FormSingUpUser {
-    #[validate(email(message = "Email is not valid"))]
+    #[validate(email(code = "email"))]
+    #[schema(format = "email")]
     pub(crate) email: String,
 
     #[validate(length(
         min = 3,
         max = 20,
-        message = "Username must be between 3 and 20 characters"
+        code = "username-length"
     ))]
+    // logins containing '@' are looked up as emails too
+    #[validate(does_not_contain(pattern = "@", code = "username-at"))]
+    #[schema(min_length = 3, max_length = 20)]
     pub(crate) username: String,
 
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    #[validate(length(min = 8, code = "password-length"))]
+    #[schema(min_length = 8, format = Password)]
     pub(crate) password: String,
 }
 
@@ -104,8 +110,98 @@ pub struct UpdateUserEmailVerify {
     pub email: String,
 }
 
-#[derive(Validate, Debug, Clone, Deserialize, Serialize)]
+#[derive(Validate, Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct FormVerifyEmail {
-    #[validate(email(message = "Email is not valid"))]
+    #[validate(email(code = "email"))]
+    #[schema(format = "email")]
     pub(crate) email: String,
 }
+
+#[derive(Validate, Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct FormPasswordReset {
+    /// A username or an email address; `email` is still accepted as the field name.
+    #[serde(alias = "email")]
+    #[validate(length(min = 1, code = "login-required"))]
+    #[schema(example = "ferris")]
+    pub(crate) login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FormLanguage {
+    pub lang: String,
+}
+
+/// Who can see a user's page at `/u/{username}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    /// Signed-in users only.
+    #[default]
+    Members,
+    /// Nobody but the user.
+    Hidden,
+}
+
+impl Visibility {
+    pub const ALL: [Visibility; 3] = [Visibility::Public, Visibility::Members, Visibility::Hidden];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Members => "members",
+            Visibility::Hidden => "hidden",
+        }
+    }
+
+    /// Whether `viewer` may see the page of the user `owner_id`.
+    pub fn allows(self, owner_id: i32, viewer: &AuthenticatedUser) -> bool {
+        if viewer.is_authenticated() && viewer.id == owner_id {
+            return true;
+        }
+        match self {
+            Visibility::Public => true,
+            Visibility::Members => viewer.is_authenticated(),
+            Visibility::Hidden => false,
+        }
+    }
+}
+
+impl TryFrom<String> for Visibility {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Visibility::ALL
+            .into_iter()
+            .find(|visibility| visibility.code() == code)
+            .ok_or_else(|| format!("unknown visibility {code:?}"))
+    }
+}
+
+/// The part of a user shown on their public page.
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicProfile {
+    pub username: String,
+    pub img: Option<String>,
+    pub bio: Option<String>,
+    #[serde(with = "date_format")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ListUser> for PublicProfile {
+    fn from(user: ListUser) -> Self {
+        PublicProfile {
+            username: user.username,
+            img: user.img,
+            bio: user.bio,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Validate, Debug, Clone, Deserialize)]
+pub struct FormPublicProfile {
+    #[validate(length(max = 500, code = "bio-length"))]
+    pub bio: String,
+    pub visibility: Visibility,
+}
@@ -0,0 +1,29 @@
+use axum::{Router, routing::{get, post}};
+use axum::middleware::from_fn;
+
+use crate::{admin, audit};
+use crate::auth::middleware::{require_admin, require_auth};
+use crate::state::AppState;
+
+pub fn build_routes(state: AppState) -> Router {
+    let admin_routes = Router::new()
+        .route("/users", get(admin::handlers::users))
+        .route("/impersonate/:id", post(admin::handlers::post_impersonate))
+        .route("/audit", get(audit::handlers::audit_events))
+        .route("/audit/export", get(audit::handlers::export_audit_events))
+        .route("/audit/verify", get(audit::handlers::verify_audit_chain))
+        .layer(from_fn(require_admin));
+
+    // the stop route is called from inside the impersonated session
+    let impersonation_routes = Router::new()
+        .route("/impersonate/stop", post(admin::handlers::post_stop_impersonate))
+        .layer(from_fn(require_auth));
+
+    Router::new().nest(
+        "/admin",
+        Router::new()
+            .merge(impersonation_routes)
+            .merge(admin_routes)
+            .with_state(state),
+    )
+}
@@ -0,0 +1,56 @@
+use axum::{Router, routing::{get, post}};
+use axum::http::{header, HeaderValue, Method};
+use axum::middleware::from_fn;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::api;
+use crate::api::docs::ApiDoc;
+use crate::auth::middleware::require_api_auth;
+use crate::state::AppState;
+
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+}
+
+pub fn build_routes(state: AppState) -> Router {
+    let auth_routes = Router::new()
+        .route(
+            "/profile",
+            get(api::handlers::get_profile).put(api::handlers::update_profile),
+        )
+        .route("/logout", post(api::handlers::logout))
+        .layer(from_fn(require_api_auth));
+
+    let public_routes = Router::new()
+        .route("/signup", post(api::handlers::signup))
+        .route("/login", post(api::handlers::login))
+        .route("/email-verify", post(api::handlers::verify_email))
+        .route("/email-verify-resend", post(api::handlers::verify_email_resend))
+        .route("/reset-password", post(api::handlers::password_reset))
+        .route("/reset-password-confirm", post(api::handlers::password_reset_confirm));
+
+    Router::new()
+        .nest(
+            "/api/v1",
+            Router::new()
+                .merge(auth_routes)
+                .merge(public_routes)
+                .layer(cors_layer(&state.config.cors_allowed_origins))
+                .with_state(state),
+        )
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
+}
@@ -1,7 +1,7 @@
-use axum::Router;
+use axum::{Router, routing::get};
 
-use tower_http::services::ServeDir;
+use crate::assets::{self, ASSETS_PREFIX};
 
 pub fn build_routes() -> Router {
-    Router::new().nest_service("/assets", ServeDir::new("assets"))
+    Router::new().route(&format!("{}/*path", ASSETS_PREFIX), get(assets::serve))
 }
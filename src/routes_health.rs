@@ -0,0 +1,65 @@
+use axum::extract::State;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router, routing::get};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::metrics;
+use crate::state::AppState;
+use crate::utils::migrate;
+
+pub fn build_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics_text))
+        .with_state(state)
+}
+
+/// The process is up and serving requests.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// The database answers and its schema matches the embedded migrations.
+async fn readyz(State(state): State<AppState>) -> Response {
+    if let Err(e) = state.db.ping().await {
+        return not_ready(format!("database: {}", e));
+    }
+    match migrate::pending(&state.db).await {
+        Ok(pending) if pending.is_empty() => Json(json!({ "status": "ready" })).into_response(),
+        Ok(pending) => not_ready(format!("pending migrations: {}", pending.join(", "))),
+        Err(e) => not_ready(format!("migrations: {}", e)),
+    }
+}
+
+fn not_ready(reason: String) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({ "status": "unavailable", "reason": reason })),
+    )
+        .into_response()
+}
+
+/// Prometheus scrape endpoint. With `metrics_token` set, scrapers must send
+/// it as a bearer token.
+async fn metrics_text(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(token) = &state.config.metrics_token {
+        let given = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // compare digests so the check does not depend on where they differ
+        if Sha256::digest(given.as_bytes()) != Sha256::digest(token.expose().as_bytes()) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(&state.db),
+    )
+        .into_response()
+}
@@ -0,0 +1,11 @@
+use axum::extract::DefaultBodyLimit;
+use axum::{Router, routing::post};
+
+use crate::security;
+use crate::security::headers::CSP_REPORT_PATH;
+
+pub fn build_routes() -> Router {
+    Router::new()
+        .route(CSP_REPORT_PATH, post(security::handlers::csp_report))
+        .layer(DefaultBodyLimit::max(16 * 1024))
+}
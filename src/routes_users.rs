@@ -0,0 +1,18 @@
+use axum::{Router, routing::get};
+use axum::middleware::from_fn;
+
+use crate::{directory, profile};
+use crate::auth::middleware::require_auth;
+use crate::state::AppState;
+
+pub fn build_routes(state: AppState) -> Router {
+    let directory_routes = Router::new()
+        .route("/users", get(directory::handlers::directory))
+        .route("/users/autocomplete", get(directory::handlers::autocomplete))
+        .layer(from_fn(require_auth));
+
+    Router::new()
+        .route("/u/:username", get(profile::handlers::get_public_profile))
+        .merge(directory_routes)
+        .with_state(state)
+}
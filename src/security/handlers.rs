@@ -0,0 +1,46 @@
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use serde_json::Value;
+use tracing::warn;
+
+/// Reports beyond this in one request are dropped.
+const MAX_REPORTS: usize = 20;
+/// Longer report values are cut, they end up in the logs verbatim.
+const MAX_VALUE_LEN: usize = 512;
+
+/// The first of `names` present in `report`: `report-uri` reports and
+/// Reporting API reports spell the same fields differently.
+fn field<'a>(report: &'a Value, names: &[&str]) -> &'a str {
+    let value = names
+        .iter()
+        .find_map(|name| report.get(*name).and_then(Value::as_str))
+        .unwrap_or("");
+    match value.char_indices().nth(MAX_VALUE_LEN) {
+        Some((end, _)) => &value[..end],
+        None => value,
+    }
+}
+
+/// Log Content-Security-Policy violations reported by browsers, either as a
+/// `{"csp-report": {...}}` object or as a Reporting API batch.
+pub async fn csp_report(body: Bytes) -> StatusCode {
+    let reports: Vec<Value> = match serde_json::from_slice(&body) {
+        Ok(Value::Array(batch)) => batch
+            .into_iter()
+            .filter(|report| report.get("type").and_then(Value::as_str) == Some("csp-violation"))
+            .filter_map(|mut report| report.get_mut("body").map(Value::take))
+            .collect(),
+        Ok(mut value) => value.get_mut("csp-report").map(Value::take).into_iter().collect(),
+        Err(_) => return StatusCode::BAD_REQUEST,
+    };
+    for report in reports.iter().take(MAX_REPORTS) {
+        warn!(
+            document = ?field(report, &["document-uri", "documentURL"]),
+            directive = ?field(report, &["effective-directive", "effectiveDirective", "violated-directive"]),
+            blocked = ?field(report, &["blocked-uri", "blockedURL"]),
+            source = ?field(report, &["source-file", "sourceFile"]),
+            "CSP violation"
+        );
+    }
+    StatusCode::NO_CONTENT
+}
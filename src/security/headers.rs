@@ -0,0 +1,99 @@
+use axum::extract::{Request, State};
+use axum::http::header::{
+    CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY,
+    STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use rand_core::{OsRng, RngCore};
+
+use crate::config::{Config, CspMode};
+use crate::state::AppState;
+
+const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
+
+/// Where browsers send CSP violation reports.
+pub const CSP_REPORT_PATH: &str = "/csp-report";
+
+/// Swagger UI styles its elements inline and cannot carry the nonce.
+const DOCS_PATH: &str = "/api/docs";
+
+/// A random value, new for every request, that marks the inline `<script>`
+/// and `<style>` tags the page itself wrote. Templates get it as
+/// `csp_nonce`.
+#[derive(Debug, Clone, Default)]
+pub struct CspNonce(pub String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        CspNonce(hex::encode(bytes))
+    }
+}
+
+fn content_security_policy(config: &Config, nonce: &CspNonce, path: &str) -> String {
+    let mut sources: Vec<String> = vec!["'self'".to_string()];
+    sources.extend(config.csp_sources.iter().cloned());
+    let fonts = sources.join(" ");
+    if path.starts_with(DOCS_PATH) {
+        sources.push("'unsafe-inline'".to_string());
+    } else {
+        sources.push(format!("'nonce-{}'", nonce.0));
+    }
+    let inline = sources.join(" ");
+    [
+        "default-src 'self'".to_string(),
+        format!("script-src {}", inline),
+        format!("style-src {}", inline),
+        format!("font-src {}", fonts),
+        "img-src 'self' data:".to_string(),
+        "connect-src 'self'".to_string(),
+        "object-src 'none'".to_string(),
+        "base-uri 'self'".to_string(),
+        "form-action 'self'".to_string(),
+        "frame-ancestors 'none'".to_string(),
+        format!("report-uri {}", CSP_REPORT_PATH),
+    ]
+    .join("; ")
+}
+
+/// Set `name` unless the handler already chose a value.
+fn set_default(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.entry(name).or_insert(value);
+    }
+}
+
+/// Add the security headers to every response and hand the request a fresh
+/// `CspNonce`. Runs before `localize`, which passes the nonce to templates.
+pub async fn security_headers(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let nonce = CspNonce::generate();
+    let path = request.uri().path().to_string();
+    request.extensions_mut().insert(nonce.clone());
+
+    let mut response = next.run(request).await;
+
+    let config = &state.config;
+    let headers = response.headers_mut();
+    let policy = content_security_policy(config, &nonce, &path);
+    match config.csp {
+        CspMode::Enforce => set_default(headers, CONTENT_SECURITY_POLICY, &policy),
+        CspMode::ReportOnly => set_default(headers, CONTENT_SECURITY_POLICY_REPORT_ONLY, &policy),
+        CspMode::Off => {}
+    }
+    if config.hsts_max_age > 0 {
+        let hsts = format!("max-age={}; includeSubDomains", config.hsts_max_age);
+        set_default(headers, STRICT_TRANSPORT_SECURITY, &hsts);
+    }
+    set_default(headers, X_FRAME_OPTIONS, "DENY");
+    set_default(headers, X_CONTENT_TYPE_OPTIONS, "nosniff");
+    set_default(headers, REFERRER_POLICY, "strict-origin-when-cross-origin");
+    set_default(
+        headers,
+        PERMISSIONS_POLICY,
+        "camera=(), microphone=(), geolocation=(), payment=(), usb=()",
+    );
+    response
+}
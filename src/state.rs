@@ -1,35 +1,83 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
-use once_cell::sync::Lazy;
-use sqlx::PgPool;
+use sqlx::migrate::MigrateError;
+use thiserror::Error;
 use tracing::error;
 
-use crate::common::AuthenticatedUser;
+use crate::audit::repository::AuditRepository;
+use crate::auth::repository::{SessionRepository, UserRepository};
+use crate::config::{Config, MigrationMode};
+use crate::storage::database::{Database, Store};
+use crate::storage::memory::MemoryStore;
+use crate::templates::TemplateRegistry;
+use crate::tokens::repository::TokenRepository;
+use crate::utils::migrate;
 
-static DATABASE_URL: Lazy<String> = Lazy::new(|| {
-    dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set")
-});
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("migrations: {0}")]
+    Migrate(#[from] MigrateError),
+    #[error("templates: {0:?}")]
+    Templates(#[from] tera::Error),
+    #[error("{} pending migration(s), run `migrate up` first: {}", .0.len(), .0.join(", "))]
+    PendingMigrations(Vec<String>),
+}
 
 impl AppState {
-    pub async fn new() -> Result<Self, sqlx::Error> {
-        let pool = PgPool::connect(&DATABASE_URL).await.map_err(|err| {
+    pub async fn new(config: Arc<Config>) -> Result<Self, StateError> {
+        let templates = Arc::new(TemplateRegistry::load(&config)?);
+
+        let db = Database::connect(config.database_url.expose()).await.map_err(|err| {
             error!("Failed to connect to the database: {:?}", err);
             err
         })?;
-        Ok(AppState {
-            db: pool,
-            user: Arc::new(Mutex::new(AuthenticatedUser::default())),
-        })
+
+        let apply = config.migrations == MigrationMode::Auto;
+        let pending = migrate::on_startup(&db, apply).await?;
+        if !pending.is_empty() {
+            return Err(StateError::PendingMigrations(pending));
+        }
+
+        let store = db.store();
+        Ok(AppState::with_store(db, store, config, templates))
+    }
+
+    /// State whose data lives in memory, so handlers run without a database.
+    pub fn in_memory(config: Arc<Config>) -> Result<Self, StateError> {
+        let templates = Arc::new(TemplateRegistry::load(&config)?);
+        Ok(AppState::with_store(Database::Memory, Arc::new(MemoryStore::new()), config, templates))
+    }
+
+    pub fn with_store(
+        db: Database,
+        store: Arc<dyn Store>,
+        config: Arc<Config>,
+        templates: Arc<TemplateRegistry>,
+    ) -> Self {
+        AppState {
+            db,
+            users: store.clone(),
+            sessions: store.clone(),
+            tokens: store.clone(),
+            audit: store,
+            config,
+            templates,
+        }
     }
 }
 
 
 
 // the application state
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
-    pub user: Arc<Mutex<AuthenticatedUser>>,
+    pub db: Database,
+    pub users: Arc<dyn UserRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub tokens: Arc<dyn TokenRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub config: Arc<Config>,
+    pub templates: Arc<TemplateRegistry>,
 }
-
-
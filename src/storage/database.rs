@@ -0,0 +1,87 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::{PgPool, SqlitePool};
+
+use crate::audit::repository::AuditRepository;
+use crate::auth::repository::{SessionRepository, UserRepository};
+use crate::storage::memory::MemoryStore;
+use crate::storage::postgres::PgStore;
+use crate::storage::sqlite::SqliteStore;
+use crate::tokens::repository::TokenRepository;
+
+/// Implemented by stores that provide every repository.
+pub trait Store: UserRepository + SessionRepository + TokenRepository + AuditRepository + 'static {}
+
+impl<S: UserRepository + SessionRepository + TokenRepository + AuditRepository + 'static> Store for S {}
+
+/// Whether `url` names a SQLite database rather than a Postgres one.
+pub fn is_sqlite(url: &str) -> bool {
+    url.starts_with("sqlite:")
+}
+
+/// Connection counts of a pool.
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
+/// The database behind the repositories, picked by the URL scheme.
+#[derive(Clone)]
+pub enum Database {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+    /// The repositories hold the data themselves.
+    Memory,
+}
+
+impl Database {
+    /// Open a `postgres://` or `sqlite:` URL; a missing SQLite file is created.
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        if is_sqlite(url) {
+            let options = SqliteConnectOptions::from_str(url)?
+                .create_if_missing(true)
+                // readers do not wait for the writer
+                .journal_mode(SqliteJournalMode::Wal);
+            Ok(Database::Sqlite(SqlitePool::connect_with(options).await?))
+        } else {
+            Ok(Database::Postgres(PgPool::connect(url).await?))
+        }
+    }
+
+    /// Every repository, backed by this database; `Memory` gets a new, empty store.
+    pub fn store(&self) -> Arc<dyn Store> {
+        match self {
+            Database::Postgres(pool) => Arc::new(PgStore::new(pool.clone())),
+            Database::Sqlite(pool) => Arc::new(SqliteStore::new(pool.clone())),
+            Database::Memory => Arc::new(MemoryStore::new()),
+        }
+    }
+
+    /// A round trip to the database.
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            Database::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+            Database::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+            Database::Memory => Ok(()),
+        }
+    }
+
+    pub fn pool_stats(&self) -> Option<PoolStats> {
+        match self {
+            Database::Postgres(pool) => Some(PoolStats {
+                size: pool.size(),
+                idle: pool.num_idle(),
+                max: pool.options().get_max_connections(),
+            }),
+            Database::Sqlite(pool) => Some(PoolStats {
+                size: pool.size(),
+                idle: pool.num_idle(),
+                max: pool.options().get_max_connections(),
+            }),
+            Database::Memory => None,
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+
+use crate::audit::chain::{checkpoint, link};
+use crate::audit::models::{AuditCheckpoint, AuditEvent, AuditFilter, NewAuditEvent};
+use crate::audit::repository::AuditRepository;
+use crate::auth::models::{ListUser, User};
+use crate::auth::repository::{SessionRepository, UserRepository};
+use crate::directory::models::{DirectoryFilter, DirectorySort};
+use crate::profile::models::{NewUser, PasswordChange, UpdateUser, UpdateUserEmailVerify, Visibility};
+use crate::tokens::models::{ApiToken, NewApiToken};
+use crate::tokens::repository::TokenRepository;
+use crate::utils::db::QueryError;
+
+#[derive(Default)]
+struct Data {
+    users: Vec<User>,
+    next_user_id: i32,
+    tokens: Vec<ApiToken>,
+    next_token_id: i32,
+    events: Vec<AuditEvent>,
+    checkpoints: Vec<AuditCheckpoint>,
+}
+
+impl Data {
+    fn user_mut(&mut self, email: &str) -> Option<&mut User> {
+        self.users.iter_mut().find(|user| user.email == email)
+    }
+
+    /// The unique constraint `email` or `username` would break, ignoring user `id`.
+    fn taken(&self, id: Option<i32>, email: &str, username: &str) -> Option<QueryError> {
+        let others = || self.users.iter().filter(|user| Some(user.id) != id);
+        let constraint = if others().any(|user| user.email.to_lowercase() == email.to_lowercase()) {
+            "users_email_lower_key"
+        } else if others().any(|user| user.username.to_lowercase() == username.to_lowercase()) {
+            "users_username_lower_key"
+        } else {
+            return None;
+        };
+        Some(QueryError::Duplicate { constraint: Some(constraint.to_string()) })
+    }
+}
+
+/// Every repository, kept in process memory; for tests and demos, as
+/// nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<Data>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn data(&self) -> MutexGuard<'_, Data> {
+        // a panic elsewhere leaves the data as consistent as Postgres would
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Filters left empty on the form match everything.
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|value| !value.is_empty())
+}
+
+fn with_activity(mut token: ApiToken) -> ApiToken {
+    token.is_active = token.active_at(Utc::now());
+    token
+}
+
+#[async_trait]
+impl UserRepository for MemoryStore {
+    async fn username_exists(&self, username: &str) -> Result<bool, QueryError> {
+        let username = username.to_lowercase();
+        Ok(self.data().users.iter().any(|user| user.username.to_lowercase() == username))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<User, QueryError> {
+        let data = self.data();
+        data.users.iter().find(|user| user.email == email).cloned().ok_or(QueryError::RowNotFound)
+    }
+
+    async fn find_by_login(&self, login: &str) -> Result<User, QueryError> {
+        let folded = login.to_lowercase();
+        let data = self.data();
+        data.users
+            .iter()
+            .filter(|user| user.email.to_lowercase() == folded || user.username.to_lowercase() == folded)
+            .min_by_key(|user| (user.email != login && user.username != login, user.id))
+            .cloned()
+            .ok_or(QueryError::RowNotFound)
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<User, QueryError> {
+        let data = self.data();
+        data.users.iter().find(|user| user.username == username).cloned().ok_or(QueryError::RowNotFound)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<User, QueryError> {
+        let data = self.data();
+        data.users.iter().find(|user| user.id == id).cloned().ok_or(QueryError::RowNotFound)
+    }
+
+    async fn list(&self) -> Result<Vec<ListUser>, QueryError> {
+        // ids only grow, so insertion order is id order
+        Ok(self.data().users.iter().cloned().map(ListUser::from).collect())
+    }
+
+    async fn directory(&self, filter: &DirectoryFilter, limit: i64) -> Result<Vec<ListUser>, QueryError> {
+        let search = filter.search.as_deref().map(str::to_lowercase);
+        let matches = |user: &User| {
+            search.as_deref().is_none_or(|search| {
+                user.username.to_lowercase().contains(search)
+                    || filter.search_email && user.email.to_lowercase().contains(search)
+            })
+        };
+        let key = |user: &User| (user.created_at, user.id);
+        let after = |user: &User| {
+            filter.after.is_none_or(|after| match filter.sort {
+                DirectorySort::Newest => key(user) < (after.created_at, after.id),
+                DirectorySort::Oldest => key(user) > (after.created_at, after.id),
+            })
+        };
+
+        let data = self.data();
+        let mut users: Vec<&User> = data
+            .users
+            .iter()
+            .filter(|user| user.is_verify && !user.is_disabled() && user.visibility != Visibility::Hidden)
+            .filter(|user| matches(user) && after(user))
+            .collect();
+        users.sort_by_key(|user| key(user));
+        if filter.sort == DirectorySort::Newest {
+            users.reverse();
+        }
+        Ok(users.into_iter().take(limit.max(0) as usize).cloned().map(ListUser::from).collect())
+    }
+
+    async fn create(&self, user: NewUser) -> Result<(), QueryError> {
+        let mut data = self.data();
+        if let Some(err) = data.taken(None, &user.email, &user.username) {
+            return Err(err);
+        }
+        data.next_user_id += 1;
+        let id = data.next_user_id;
+        data.users.push(User {
+            id,
+            password: user.password,
+            email: user.email,
+            username: user.username,
+            img: None,
+            is_verify: user.is_verify,
+            is_admin: false,
+            created_at: user.created_at,
+            updated_at: None,
+            disabled_at: None,
+            sessions_revoked_at: None,
+            locale: None,
+            bio: None,
+            visibility: Visibility::default(),
+        });
+        Ok(())
+    }
+
+    async fn set_verified(&self, update: UpdateUserEmailVerify) -> Result<(), QueryError> {
+        if let Some(user) = self.data().user_mut(&update.email) {
+            user.is_verify = update.is_verify;
+            user.updated_at = update.updated_at;
+        }
+        Ok(())
+    }
+
+    async fn update_password(&self, change: PasswordChange) -> Result<(), QueryError> {
+        if let Some(user) = self.data().user_mut(&change.email) {
+            user.password = change.password;
+            user.updated_at = change.updated_at;
+        }
+        Ok(())
+    }
+
+    async fn update_profile(&self, id: i32, update: UpdateUser) -> Result<(), QueryError> {
+        let mut data = self.data();
+        if let Some(err) = data.taken(Some(id), &update.email, &update.username) {
+            return Err(err);
+        }
+        if let Some(user) = data.users.iter_mut().find(|user| user.id == id) {
+            user.is_verify = user.is_verify && user.email == update.email;
+            user.email = update.email;
+            user.username = update.username;
+            user.updated_at = update.updated_at;
+        }
+        Ok(())
+    }
+
+    async fn change_email(&self, id: i32, email: &str) -> Result<(), QueryError> {
+        let mut data = self.data();
+        let username = match data.users.iter().find(|user| user.id == id) {
+            Some(user) => user.username.clone(),
+            None => return Ok(()),
+        };
+        if let Some(err) = data.taken(Some(id), email, &username) {
+            return Err(err);
+        }
+        if let Some(user) = data.users.iter_mut().find(|user| user.id == id) {
+            user.email = email.to_string();
+            user.is_verify = true;
+            user.updated_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    async fn set_admin(&self, email: &str, is_admin: bool) -> Result<u64, QueryError> {
+        Ok(match self.data().user_mut(email) {
+            Some(user) => {
+                user.is_admin = is_admin;
+                user.updated_at = Some(Utc::now());
+                1
+            }
+            None => 0,
+        })
+    }
+
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<u64, QueryError> {
+        Ok(match self.data().user_mut(email) {
+            Some(user) => {
+                user.disabled_at = if disabled { user.disabled_at.or(Some(Utc::now())) } else { None };
+                user.updated_at = Some(Utc::now());
+                1
+            }
+            None => 0,
+        })
+    }
+
+    async fn set_locale(&self, id: i32, locale: &str) -> Result<u64, QueryError> {
+        Ok(match self.data().users.iter_mut().find(|user| user.id == id) {
+            Some(user) => {
+                user.locale = Some(locale.to_string());
+                1
+            }
+            None => 0,
+        })
+    }
+
+    async fn set_public_profile(&self, id: i32, bio: Option<&str>, visibility: Visibility) -> Result<u64, QueryError> {
+        Ok(match self.data().users.iter_mut().find(|user| user.id == id) {
+            Some(user) => {
+                user.bio = bio.map(str::to_string);
+                user.visibility = visibility;
+                user.updated_at = Some(Utc::now());
+                1
+            }
+            None => 0,
+        })
+    }
+
+    async fn delete(&self, email: &str) -> Result<(), QueryError> {
+        let mut data = self.data();
+        if let Some(index) = data.users.iter().position(|user| user.email == email) {
+            let user = data.users.remove(index);
+            data.tokens.retain(|token| token.user_id != user.id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MemoryStore {
+    async fn revoke_all(&self, email: &str) -> Result<u64, QueryError> {
+        Ok(match self.data().user_mut(email) {
+            Some(user) => {
+                user.sessions_revoked_at = Some(Utc::now());
+                1
+            }
+            None => 0,
+        })
+    }
+}
+
+#[async_trait]
+impl TokenRepository for MemoryStore {
+    async fn create(&self, token: NewApiToken) -> Result<i32, QueryError> {
+        let mut data = self.data();
+        if !data.users.iter().any(|user| user.id == token.user_id) {
+            return Err(QueryError::ForeignKey { constraint: Some("api_tokens_user_id_fkey".to_string()) });
+        }
+        if data.tokens.iter().any(|other| other.prefix == token.prefix) {
+            return Err(QueryError::Duplicate { constraint: Some("api_tokens_prefix_key".to_string()) });
+        }
+        data.next_token_id += 1;
+        let id = data.next_token_id;
+        data.tokens.push(ApiToken {
+            id,
+            user_id: token.user_id,
+            name: token.name,
+            prefix: token.prefix,
+            token_hash: token.token_hash,
+            scopes: token.scopes,
+            created_at: Utc::now(),
+            expires_at: token.expires_at,
+            last_used_at: None,
+            last_used_ip: None,
+            revoked_at: None,
+            is_active: true,
+        });
+        Ok(id)
+    }
+
+    async fn list(&self, user_id: i32) -> Result<Vec<ApiToken>, QueryError> {
+        let data = self.data();
+        Ok(data
+            .tokens
+            .iter()
+            .rev()
+            .filter(|token| token.user_id == user_id)
+            .cloned()
+            .map(with_activity)
+            .collect())
+    }
+
+    async fn find_by_prefix(&self, prefix: &str) -> Result<ApiToken, QueryError> {
+        let data = self.data();
+        let token = data.tokens.iter().find(|token| token.prefix == prefix).cloned();
+        token.map(with_activity).ok_or(QueryError::RowNotFound)
+    }
+
+    async fn touch(&self, id: i32, ip: Option<&str>) -> Result<(), QueryError> {
+        let now = Utc::now();
+        if let Some(token) = self.data().tokens.iter_mut().find(|token| token.id == id) {
+            let recent = token.last_used_at.is_some_and(|at| at >= now - Duration::minutes(1));
+            if !recent || token.last_used_ip.as_deref() != ip {
+                token.last_used_at = Some(now);
+                token.last_used_ip = ip.map(str::to_string);
+            }
+        }
+        Ok(())
+    }
+
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<bool, QueryError> {
+        let mut data = self.data();
+        let token = data
+            .tokens
+            .iter_mut()
+            .find(|token| token.id == id && token.user_id == user_id && token.revoked_at.is_none());
+        Ok(match token {
+            Some(token) => {
+                token.revoked_at = Some(Utc::now());
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryStore {
+    async fn append(&self, event: &NewAuditEvent) -> Result<i64, QueryError> {
+        // the lock serializes appends
+        let mut data = self.data();
+        let prev_hash = data.events.last().and_then(|last| last.hash.clone());
+        let (created_at, hash) = link(prev_hash.as_deref(), event);
+        // every record here is chained, so ids and positions coincide
+        let id = data.events.len() as i64 + 1;
+        if let Some(signature) = checkpoint(id, id, &hash) {
+            let checkpoint_id = data.checkpoints.len() as i64 + 1;
+            data.checkpoints.push(AuditCheckpoint {
+                id: checkpoint_id,
+                event_id: id,
+                hash: hash.clone(),
+                signature,
+            });
+        }
+        data.events.push(AuditEvent {
+            id,
+            created_at,
+            event: event.event.clone(),
+            actor_id: event.actor_id,
+            subject_id: event.subject_id,
+            subject: event.subject.clone(),
+            ip: event.ip.clone(),
+            user_agent: event.user_agent.clone(),
+            request_id: event.request_id.clone(),
+            details: event.details.clone(),
+            prev_hash,
+            hash: Some(hash),
+            chain_position: Some(id),
+        });
+        Ok(id)
+    }
+
+    async fn chain(&self, after_id: i64, limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        let data = self.data();
+        Ok(data
+            .events
+            .iter()
+            .filter(|event| event.id > after_id)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, QueryError> {
+        Ok(self.data().checkpoints.clone())
+    }
+
+    async fn events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        let data = self.data();
+        Ok(data
+            .events
+            .iter()
+            .rev()
+            .filter(|event| non_empty(&filter.event).is_none_or(|name| event.event == name))
+            .filter(|event| filter.actor_id.is_none_or(|id| event.actor_id == Some(id)))
+            .filter(|event| filter.subject_id.is_none_or(|id| event.subject_id == Some(id)))
+            .filter(|event| non_empty(&filter.subject).is_none_or(|subject| event.subject.as_deref() == Some(subject)))
+            .filter(|event| filter.before_id.is_none_or(|id| event.id < id))
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+}
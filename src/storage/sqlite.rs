@@ -0,0 +1,434 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::types::Json;
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use crate::audit::chain::{checkpoint, link};
+use crate::audit::models::{AuditCheckpoint, AuditEvent, AuditFilter, NewAuditEvent};
+use crate::audit::repository::AuditRepository;
+use crate::auth::models::{ListUser, User};
+use crate::auth::repository::{SessionRepository, UserRepository};
+use crate::directory::models::{contains_pattern, DirectoryFilter};
+use crate::profile::models::{NewUser, PasswordChange, UpdateUser, UpdateUserEmailVerify, Visibility};
+use crate::tokens::models::{ApiToken, NewApiToken};
+use crate::tokens::repository::TokenRepository;
+use crate::utils::db::QueryError;
+
+/// A row of `api_tokens`, whose scopes SQLite keeps as a JSON array.
+#[derive(FromRow)]
+struct TokenRow {
+    id: i32,
+    user_id: i32,
+    name: String,
+    prefix: String,
+    token_hash: String,
+    scopes: Json<Vec<String>>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    last_used_ip: Option<String>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<TokenRow> for ApiToken {
+    fn from(row: TokenRow) -> Self {
+        let mut token = ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            prefix: row.prefix,
+            token_hash: row.token_hash,
+            scopes: row.scopes.0,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            last_used_ip: row.last_used_ip,
+            revoked_at: row.revoked_at,
+            is_active: false,
+        };
+        token.is_active = token.active_at(Utc::now());
+        token
+    }
+}
+
+/// Every repository, backed by SQLite. Timestamps are bound from here
+/// rather than taken from SQL, so they share the encoding of the columns.
+pub struct SqliteStore {
+    pool: SqlitePool,
+    /// Serializes audit appends, which read the chain head before writing.
+    audit_lock: Mutex<()>,
+}
+
+impl SqliteStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        SqliteStore { pool, audit_lock: Mutex::new(()) }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteStore {
+    #[instrument(skip_all)]
+    async fn username_exists(&self, username: &str) -> Result<bool, QueryError> {
+        let query = "SELECT EXISTS(SELECT 1 FROM users WHERE lower(username) = lower($1))";
+        let exists: (bool,) = sqlx::query_as(query).bind(username).fetch_one(&self.pool).await?;
+        Ok(exists.0)
+    }
+
+    #[instrument(skip_all)]
+    async fn find_by_email(&self, email: &str) -> Result<User, QueryError> {
+        let query = "SELECT * FROM users WHERE email = $1";
+        Ok(sqlx::query_as(query).bind(email).fetch_one(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn find_by_login(&self, login: &str) -> Result<User, QueryError> {
+        let query = "
+            SELECT * FROM users
+            WHERE lower(email) = lower($1) OR lower(username) = lower($1)
+            ORDER BY (email = $1 OR username = $1) DESC, id
+            LIMIT 1
+        ";
+        Ok(sqlx::query_as(query).bind(login).fetch_one(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn find_by_username(&self, username: &str) -> Result<User, QueryError> {
+        let query = "SELECT * FROM users WHERE username = $1";
+        Ok(sqlx::query_as(query).bind(username).fetch_one(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn find_by_id(&self, id: i32) -> Result<User, QueryError> {
+        let query = "SELECT * FROM users WHERE id = $1";
+        Ok(sqlx::query_as(query).bind(id).fetch_one(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn list(&self) -> Result<Vec<ListUser>, QueryError> {
+        let query = "SELECT * FROM users ORDER BY id";
+        Ok(sqlx::query_as(query).fetch_all(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn directory(&self, filter: &DirectoryFilter, limit: i64) -> Result<Vec<ListUser>, QueryError> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT * FROM users WHERE is_verify AND disabled_at IS NULL AND visibility <> 'hidden'",
+        );
+        if let Some(search) = &filter.search {
+            // no trigrams here: LIKE, which ignores ASCII case, finds substrings only
+            let pattern = contains_pattern(search);
+            query.push(" AND (username LIKE ").push_bind(pattern.clone()).push(" ESCAPE '\\'");
+            if filter.search_email {
+                query.push(" OR email LIKE ").push_bind(pattern).push(" ESCAPE '\\'");
+            }
+            query.push(")");
+        }
+        let (keyset, direction) = filter.sort.keyset();
+        if let Some(after) = filter.after {
+            query.push(format!(" AND (created_at, id) {} (", keyset));
+            query.push_bind(after.created_at).push(", ").push_bind(after.id).push(")");
+        }
+        query.push(format!(" ORDER BY created_at {0}, id {0} LIMIT ", direction)).push_bind(limit);
+
+        Ok(query.build_query_as().fetch_all(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn create(&self, user: NewUser) -> Result<(), QueryError> {
+        let query = "
+            INSERT INTO users (email, username, password, is_verify, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+        ";
+        sqlx::query(query)
+            .bind(&user.email)
+            .bind(&user.username)
+            .bind(&user.password)
+            .bind(user.is_verify)
+            .bind(user.created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn set_verified(&self, user: UpdateUserEmailVerify) -> Result<(), QueryError> {
+        let query = "UPDATE users SET is_verify = $2, updated_at = $3 WHERE email = $1";
+        sqlx::query(query)
+            .bind(&user.email)
+            .bind(user.is_verify)
+            .bind(user.updated_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn update_password(&self, user: PasswordChange) -> Result<(), QueryError> {
+        let query = "UPDATE users SET password = $2, updated_at = $3 WHERE email = $1";
+        sqlx::query(query)
+            .bind(&user.email)
+            .bind(&user.password)
+            .bind(user.updated_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn update_profile(&self, id: i32, user: UpdateUser) -> Result<(), QueryError> {
+        let query = "
+            UPDATE users
+            SET email = $2, username = $3, updated_at = $4, is_verify = is_verify AND email = $2
+            WHERE id = $1
+        ";
+        sqlx::query(query)
+            .bind(id)
+            .bind(&user.email)
+            .bind(&user.username)
+            .bind(user.updated_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn change_email(&self, id: i32, email: &str) -> Result<(), QueryError> {
+        let query = "UPDATE users SET email = $2, is_verify = TRUE, updated_at = $3 WHERE id = $1";
+        sqlx::query(query)
+            .bind(id)
+            .bind(email)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn set_admin(&self, email: &str, is_admin: bool) -> Result<u64, QueryError> {
+        let query = "UPDATE users SET is_admin = $2, updated_at = $3 WHERE email = $1";
+        let result = sqlx::query(query)
+            .bind(email)
+            .bind(is_admin)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<u64, QueryError> {
+        let query = "
+            UPDATE users SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, $3) END, updated_at = $3
+            WHERE email = $1
+        ";
+        let result = sqlx::query(query)
+            .bind(email)
+            .bind(disabled)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn set_locale(&self, id: i32, locale: &str) -> Result<u64, QueryError> {
+        let query = "UPDATE users SET locale = $2 WHERE id = $1";
+        let result = sqlx::query(query).bind(id).bind(locale).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn set_public_profile(&self, id: i32, bio: Option<&str>, visibility: Visibility) -> Result<u64, QueryError> {
+        let query = "UPDATE users SET bio = $2, visibility = $3, updated_at = $4 WHERE id = $1";
+        let result = sqlx::query(query)
+            .bind(id)
+            .bind(bio)
+            .bind(visibility.code())
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete(&self, email: &str) -> Result<(), QueryError> {
+        // api_tokens rows go with the user (ON DELETE CASCADE)
+        let query = "DELETE FROM users WHERE email = $1";
+        sqlx::query(query).bind(email).execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionRepository for SqliteStore {
+    #[instrument(skip_all)]
+    async fn revoke_all(&self, email: &str) -> Result<u64, QueryError> {
+        let query = "UPDATE users SET sessions_revoked_at = $2 WHERE email = $1";
+        let result = sqlx::query(query).bind(email).bind(Utc::now()).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl TokenRepository for SqliteStore {
+    #[instrument(skip_all)]
+    async fn create(&self, token: NewApiToken) -> Result<i32, QueryError> {
+        let query = "
+            INSERT INTO api_tokens (user_id, name, prefix, token_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+        ";
+        let id: (i32,) = sqlx::query_as(query)
+            .bind(token.user_id)
+            .bind(&token.name)
+            .bind(&token.prefix)
+            .bind(&token.token_hash)
+            .bind(Json(&token.scopes))
+            .bind(Utc::now())
+            .bind(token.expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id.0)
+    }
+
+    #[instrument(skip_all)]
+    async fn list(&self, user_id: i32) -> Result<Vec<ApiToken>, QueryError> {
+        let query = "SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY id DESC";
+        let rows: Vec<TokenRow> = sqlx::query_as(query).bind(user_id).fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(ApiToken::from).collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn find_by_prefix(&self, prefix: &str) -> Result<ApiToken, QueryError> {
+        let query = "SELECT * FROM api_tokens WHERE prefix = $1";
+        let row: TokenRow = sqlx::query_as(query).bind(prefix).fetch_one(&self.pool).await?;
+        Ok(row.into())
+    }
+
+    #[instrument(skip_all)]
+    async fn touch(&self, id: i32, ip: Option<&str>) -> Result<(), QueryError> {
+        // RFC 3339 text in UTC sorts like the time it encodes
+        let now = Utc::now();
+        let query = "
+            UPDATE api_tokens SET last_used_at = $3, last_used_ip = $2
+            WHERE id = $1
+              AND (last_used_at IS NULL
+                   OR last_used_at < $4
+                   OR last_used_ip IS NOT $2)
+        ";
+        sqlx::query(query)
+            .bind(id)
+            .bind(ip)
+            .bind(now)
+            .bind(now - Duration::minutes(1))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<bool, QueryError> {
+        let query = "UPDATE api_tokens SET revoked_at = $3 WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL";
+        let result = sqlx::query(query)
+            .bind(id)
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for SqliteStore {
+    #[instrument(skip_all)]
+    async fn append(&self, event: &NewAuditEvent) -> Result<i64, QueryError> {
+        let _guard = self.audit_lock.lock().await;
+        let mut tx = self.pool.begin().await?;
+
+        let query = "SELECT hash, chain_position FROM audit_events ORDER BY id DESC LIMIT 1";
+        let last: Option<(Option<String>, Option<i64>)> = sqlx::query_as(query).fetch_optional(&mut *tx).await?;
+        let (prev_hash, last_position) = last.unwrap_or_default();
+        let position = match last_position {
+            Some(position) => position + 1,
+            None => {
+                let query = "SELECT count(*) FROM audit_events WHERE hash IS NOT NULL";
+                let count: (i64,) = sqlx::query_as(query).fetch_one(&mut *tx).await?;
+                count.0 + 1
+            }
+        };
+        let (created_at, hash) = link(prev_hash.as_deref(), event);
+
+        let query = "
+            INSERT INTO audit_events (event, actor_id, subject_id, subject, ip, user_agent, request_id, details, created_at, prev_hash, hash, chain_position)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id
+        ";
+        let id: (i64,) = sqlx::query_as(query)
+            .bind(&event.event)
+            .bind(event.actor_id)
+            .bind(event.subject_id)
+            .bind(&event.subject)
+            .bind(&event.ip)
+            .bind(&event.user_agent)
+            .bind(&event.request_id)
+            .bind(&event.details)
+            .bind(created_at)
+            .bind(&prev_hash)
+            .bind(&hash)
+            .bind(position)
+            .fetch_one(&mut *tx)
+            .await?;
+        let id = id.0;
+
+        if let Some(signature) = checkpoint(position, id, &hash) {
+            let query = "INSERT INTO audit_checkpoints (event_id, hash, signature) VALUES ($1, $2, $3)";
+            sqlx::query(query)
+                .bind(id)
+                .bind(&hash)
+                .bind(&signature)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    #[instrument(skip_all)]
+    async fn chain(&self, after_id: i64, limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        let query = "SELECT * FROM audit_events WHERE id > $1 ORDER BY id LIMIT $2";
+        Ok(sqlx::query_as(query).bind(after_id).bind(limit).fetch_all(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, QueryError> {
+        let query = "SELECT * FROM audit_checkpoints ORDER BY event_id, id";
+        Ok(sqlx::query_as(query).fetch_all(&self.pool).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM audit_events WHERE true");
+        if let Some(event) = filter.event.as_ref().filter(|e| !e.is_empty()) {
+            query.push(" AND event = ").push_bind(event);
+        }
+        if let Some(actor_id) = filter.actor_id {
+            query.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(subject_id) = filter.subject_id {
+            query.push(" AND subject_id = ").push_bind(subject_id);
+        }
+        if let Some(subject) = filter.subject.as_ref().filter(|s| !s.is_empty()) {
+            query.push(" AND subject = ").push_bind(subject);
+        }
+        if let Some(before_id) = filter.before_id {
+            query.push(" AND id < ").push_bind(before_id);
+        }
+        query.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        Ok(query.build_query_as().fetch_all(&self.pool).await?)
+    }
+}
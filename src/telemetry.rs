@@ -0,0 +1,90 @@
+use axum::extract::Request;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use crate::config::LogFormat;
+
+const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Keeps the trace exporter alive; dropping it flushes the spans still
+/// queued for the collector.
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("error: flushing traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber: log lines in `format`, levels from
+/// `RUST_LOG` (`info` by default), and spans exported to `otlp_endpoint`
+/// when one is given.
+pub fn init(format: LogFormat, otlp_endpoint: Option<&str>) -> Telemetry {
+    let provider = otlp_endpoint.and_then(|endpoint| match tracer_provider(endpoint) {
+        Ok(provider) => Some(provider),
+        Err(e) => {
+            eprintln!("error: trace export to {} disabled: {}", endpoint, e);
+            None
+        }
+    });
+    let otel = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+
+    let output = match format {
+        LogFormat::Pretty => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer().json().boxed(),
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    tracing_subscriber::registry()
+        .with(otel)
+        .with(output)
+        .with(filter)
+        .init();
+    Telemetry { provider }
+}
+
+fn tracer_provider(endpoint: &str) -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint))
+        .build()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build())
+}
+
+/// The span every request runs in. `SetRequestIdLayer` has already filled
+/// in `x-request-id`; `route` and `user_id` are recorded once known.
+pub fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id,
+        route = Empty,
+        user_id = Empty,
+        impersonator_id = Empty,
+    )
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use tera::{Context, Tera};
+use tracing::{error, info};
+
+use crate::assets;
+use crate::config::Config;
+use crate::i18n::{self, Locale};
+
+/// Every template: its name, its path under `templates/` and the copy
+/// embedded in the binary.
+const TEMPLATES: &[(&str, &str, &str)] = &[
+    ("base.html", "base.html", include_str!("../templates/base.html")),
+    ("navbar.html", "navbar.html", include_str!("../templates/navbar.html")),
+    ("footer.html", "footer.html", include_str!("../templates/footer.html")),
+    ("messages.html", "messages.html", include_str!("../templates/messages.html")),
+    ("index", "index.html", include_str!("../templates/index.html")),
+    ("login", "auth/login.html", include_str!("../templates/auth/login.html")),
+    ("logout", "auth/logout.html", include_str!("../templates/auth/logout.html")),
+    ("signup", "auth/signup.html", include_str!("../templates/auth/signup.html")),
+    ("email-verify-resend", "auth/email-verify-resend.html", include_str!("../templates/auth/email-verify-resend.html")),
+    ("email-verify", "auth/email-verify.html", include_str!("../templates/auth/email-verify.html")),
+    ("reset-password", "auth/reset-password.html", include_str!("../templates/auth/reset-password.html")),
+    ("reset-password-confirm", "auth/reset-password-confirm.html", include_str!("../templates/auth/reset-password-confirm.html")),
+    ("detail", "profile/detail.html", include_str!("../templates/profile/detail.html")),
+    ("update", "profile/update.html", include_str!("../templates/profile/update.html")),
+    ("password_change", "profile/password-change.html", include_str!("../templates/profile/password-change.html")),
+    ("delete-user", "profile/delete.html", include_str!("../templates/profile/delete.html")),
+    ("tokens", "profile/tokens.html", include_str!("../templates/profile/tokens.html")),
+    ("public_profile", "profile/public.html", include_str!("../templates/profile/public.html")),
+    ("public_profile_settings", "profile/public-settings.html", include_str!("../templates/profile/public-settings.html")),
+    ("directory", "profile/directory.html", include_str!("../templates/profile/directory.html")),
+    ("admin-users", "admin/users.html", include_str!("../templates/admin/users.html")),
+    ("admin-audit", "admin/audit.html", include_str!("../templates/admin/audit.html")),
+    ("admin-audit-verify", "admin/audit-verify.html", include_str!("../templates/admin/audit-verify.html")),
+    ("404", "errors/404.html", include_str!("../templates/errors/404.html")),
+    ("500", "errors/500.html", include_str!("../templates/errors/500.html")),
+];
+
+/// The templates of every router, shared through `AppState`, with one Tera
+/// instance per locale so that `t()` needs no language argument.
+///
+/// In production the embedded copies are used, except where the override
+/// directory has a file at the same path. With `templates_reload` the
+/// copies are read from the source tree instead, and re-read whenever a
+/// file changes.
+pub struct TemplateRegistry {
+    tera: RwLock<Vec<Tera>>,
+    /// Source tree to read from in development.
+    source_dir: Option<PathBuf>,
+    override_dir: Option<PathBuf>,
+    loaded_at: Mutex<SystemTime>,
+}
+
+impl TemplateRegistry {
+    pub fn load(config: &Config) -> tera::Result<Self> {
+        let source_dir = config
+            .templates_reload
+            .then(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("templates"));
+        let mut registry = TemplateRegistry {
+            tera: RwLock::new(Vec::new()),
+            source_dir,
+            override_dir: config.templates_dir.clone(),
+            loaded_at: Mutex::new(SystemTime::now()),
+        };
+        registry.tera = RwLock::new(registry.build()?);
+        Ok(registry)
+    }
+
+    /// Files that replace an embedded template, in order of precedence.
+    fn candidates<'a>(&'a self, path: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+        self.override_dir
+            .iter()
+            .chain(self.source_dir.iter())
+            .map(move |dir| dir.join(path))
+    }
+
+    fn build(&self) -> tera::Result<Vec<Tera>> {
+        let mut sources = Vec::with_capacity(TEMPLATES.len());
+        for (name, path, embedded) in TEMPLATES {
+            let content = match self.candidates(path).find(|file| file.is_file()) {
+                Some(file) => std::fs::read_to_string(&file).map_err(|e| {
+                    tera::Error::msg(format!("cannot read {}: {}", file.display(), e))
+                })?,
+                None => embedded.to_string(),
+            };
+            sources.push((*name, content));
+        }
+        Locale::all()
+            .map(|locale| {
+                let mut tera = Tera::default();
+                // most names carry no .html suffix, which Tera would need to
+                // escape them on its own
+                tera.autoescape_on(TEMPLATES.iter().map(|(name, _, _)| *name).collect());
+                tera.register_function("t", i18n::tera_function(locale));
+                tera.register_function("asset_url", assets::tera_function());
+                tera.add_raw_templates(sources.clone())?;
+                Ok(tera)
+            })
+            .collect()
+    }
+
+    /// Newest modification time among the files the templates come from.
+    fn newest_change(&self) -> Option<SystemTime> {
+        TEMPLATES
+            .iter()
+            .flat_map(|(_, path, _)| self.candidates(path))
+            .filter_map(|file| file.metadata().and_then(|meta| meta.modified()).ok())
+            .max()
+    }
+
+    /// Rebuild after a template file changed; a broken edit is logged and
+    /// the previous templates stay in use.
+    fn reload_if_changed(&self) {
+        let changed = match self.newest_change() {
+            Some(changed) => changed,
+            None => return,
+        };
+        let mut loaded_at = self.loaded_at.lock().unwrap_or_else(|e| e.into_inner());
+        if changed <= *loaded_at {
+            return;
+        }
+        *loaded_at = SystemTime::now();
+        match self.build() {
+            Ok(tera) => {
+                *self.tera.write().unwrap_or_else(|e| e.into_inner()) = tera;
+                info!("Reloaded templates");
+            }
+            Err(e) => error!("Error reloading templates: {:?}", e),
+        }
+    }
+
+    pub fn render(&self, locale: Locale, name: &str, context: &Context) -> tera::Result<String> {
+        if self.source_dir.is_some() {
+            self.reload_if_changed();
+        }
+        let tera = self.tera.read().unwrap_or_else(|e| e.into_inner());
+        tera[locale.index()].render(name, context)
+    }
+}
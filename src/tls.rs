@@ -0,0 +1,133 @@
+use std::time::{Duration, SystemTime};
+
+use axum::extract::Request;
+use axum::http::header::HOST;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::{error, info};
+
+use crate::config::TlsFiles;
+
+/// How often the certificate files are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Load the certificate chain and key for `axum_server::bind_rustls`.
+pub async fn load(files: &TlsFiles) -> std::io::Result<RustlsConfig> {
+    // rustls wants one crypto provider per process; a second install is a no-op
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&files.cert, &files.key).await
+}
+
+fn modified(files: &TlsFiles) -> Option<SystemTime> {
+    [&files.cert, &files.key]
+        .into_iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// What an operator sends to ask for a reload.
+const RELOAD_SIGNAL: &str = "SIGHUP";
+
+/// Listens for `RELOAD_SIGNAL`. There is none outside Unix, where only file
+/// changes reload the certificate.
+struct ReloadSignal {
+    #[cfg(unix)]
+    hangup: Option<tokio::signal::unix::Signal>,
+}
+
+impl ReloadSignal {
+    #[cfg(unix)]
+    fn new() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+        use tracing::warn;
+
+        let hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(e) => {
+                warn!("Cannot listen for SIGHUP, only file changes reload TLS: {:?}", e);
+                None
+            }
+        };
+        ReloadSignal { hangup }
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Self {
+        ReloadSignal {}
+    }
+
+    /// Resolve on the next signal; never, when it cannot be listened for.
+    #[cfg(unix)]
+    async fn recv(&mut self) {
+        let received = match self.hangup.as_mut() {
+            Some(hangup) => hangup.recv().await.is_some(),
+            None => false,
+        };
+        if !received {
+            std::future::pending().await
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) {
+        std::future::pending().await
+    }
+}
+
+/// Reload the certificate on `RELOAD_SIGNAL` and whenever its files change,
+/// so a renewal needs no restart. A pair that fails to load is logged and
+/// the current one kept until the files change again.
+pub fn watch(tls: RustlsConfig, files: TlsFiles) {
+    tokio::spawn(async move {
+        let mut reload = ReloadSignal::new();
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut last = modified(&files);
+        loop {
+            let reason = tokio::select! {
+                () = reload.recv() => RELOAD_SIGNAL,
+                _ = interval.tick() => {
+                    let now = modified(&files);
+                    if now == last {
+                        continue;
+                    }
+                    last = now;
+                    "file change"
+                }
+            };
+            match tls.reload_from_pem_file(&files.cert, &files.key).await {
+                Ok(()) => info!("Reloaded TLS certificate after {}", reason),
+                Err(e) => error!("Failed to reload TLS certificate after {}: {}", reason, e),
+            }
+        }
+    });
+}
+
+/// The app for the plain-HTTP listener: every request is sent to the same
+/// URL over HTTPS on `https_port`.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect_to_https(&request, https_port) })
+}
+
+fn redirect_to_https(request: &Request, https_port: u16) -> Response {
+    let host = match request.headers().get(HOST).and_then(|value| value.to_str().ok()) {
+        Some(host) => host,
+        None => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    // drop the port of the plain listener, keeping IPv6 brackets intact
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let authority = if https_port == 443 {
+        name.to_string()
+    } else {
+        format!("{}:{}", name, https_port)
+    };
+    let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+    match Uri::builder().scheme("https").authority(authority).path_and_query(path).build() {
+        Ok(uri) => Redirect::permanent(&uri.to_string()).into_response(),
+        Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
@@ -0,0 +1,97 @@
+use axum::{
+    Extension,
+    extract::{Form, Path, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{Duration, Utc};
+use validator::Validate;
+
+use crate::audit::log::record;
+use crate::audit::models::NewAuditEvent;
+use crate::common::{page_context, render, AuthenticatedUser, RequestMeta, Templates};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::tokens::models::{FormNewToken, NewApiToken, SCOPES};
+use crate::tokens::secret;
+use crate::utils::flash::Flash;
+use crate::utils::message::{field_error, handle_errors};
+
+async fn tokens_context(state: &AppState, user: &AuthenticatedUser) -> Result<tera::Context, AppError> {
+    let mut context = page_context(user);
+
+    let tokens = state.tokens.list(user.id).await?;
+    context.insert("tokens", &tokens);
+    context.insert("scopes", &SCOPES);
+    Ok(context)
+}
+
+pub async fn get_tokens(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AppError> {
+    let context = tokens_context(&state, &user).await?;
+    render(&templates, "tokens", &context)
+}
+
+pub async fn post_new_token(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+    Form(form): Form<FormNewToken>,
+) -> Result<Response, AppError> {
+    if let Err(errors) = form.validate() {
+        let mut context = tokens_context(&state, &user).await?;
+        context.insert("messages", &handle_errors(errors, templates.locale).await);
+        return Ok(render(&templates, "tokens", &context)?.into_response());
+    }
+    let scopes = form.scopes();
+    if scopes.is_empty() {
+        let mut context = tokens_context(&state, &user).await?;
+        context.insert("field_errors", &field_error("scopes", &templates.t("tokens-choose-scope")));
+        return Ok(render(&templates, "tokens", &context)?.into_response());
+    }
+
+    let generated = secret::generate();
+    let new_token = NewApiToken {
+        user_id: user.id,
+        name: form.name,
+        prefix: generated.prefix.clone(),
+        token_hash: generated.hash,
+        scopes: scopes.clone(),
+        expires_at: Utc::now() + Duration::days(form.expires_in_days),
+    };
+    let id = state.tokens.create(new_token.clone()).await?;
+    record(state.audit.as_ref(), NewAuditEvent::new("token.create", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)
+        .details(&format!("token {} ({}) scopes {}", id, generated.prefix, scopes.join(" ")))).await;
+
+    // the only time the full token is ever shown
+    let mut context = tokens_context(&state, &user).await?;
+    context.insert("new_token", &generated.token);
+    context.insert("new_token_name", &new_token.name);
+    Ok(render(&templates, "tokens", &context)?.into_response())
+}
+
+pub async fn post_revoke_token(
+    State(state): State<AppState>,
+    Extension(templates): Extension<Templates>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    user: AuthenticatedUser,
+    meta: RequestMeta,
+) -> Result<Response, AppError> {
+    if !state.tokens.revoke(user.id, id).await? {
+        return Err(AppError::NotFound);
+    }
+    record(state.audit.as_ref(), NewAuditEvent::new("token.revoke", &meta)
+        .actor(user.id)
+        .subject_id(user.id)
+        .subject(&user.email)
+        .details(&format!("token {}", id))).await;
+    flash.success(templates.t("flash-token-revoked"));
+    Ok(Redirect::to("/account/tokens").into_response())
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator_derive::Validate;
+
+use crate::utils::date_config::{date_format, option_date_format};
+
+/// Scopes a personal access token can be granted, with their descriptions.
+pub const SCOPES: [(&str, &str); 2] = [
+    ("profile:read", "Read your profile"),
+    ("profile:write", "Update your email and username"),
+];
+
+/// A row of the `api_tokens` table; the secret itself is never stored.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    #[serde(with = "date_format")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "date_format")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(serialize_with = "option_date_format::serialize")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+    #[serde(serialize_with = "option_date_format::serialize")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+}
+
+impl ApiToken {
+    /// Whether the token is neither revoked nor expired at `now`.
+    pub fn active_at(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewApiToken {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Validate, Debug, Clone, Deserialize)]
+pub struct FormNewToken {
+    #[validate(length(min = 1, max = 64, code = "token-name-length"))]
+    pub name: String,
+    #[validate(range(min = 1, max = 365, code = "token-expiry-range"))]
+    pub expires_in_days: i64,
+    #[serde(rename = "profile:read")]
+    pub profile_read: Option<String>,
+    #[serde(rename = "profile:write")]
+    pub profile_write: Option<String>,
+}
+
+impl FormNewToken {
+    /// The scopes ticked on the form.
+    pub fn scopes(&self) -> Vec<String> {
+        [("profile:read", &self.profile_read), ("profile:write", &self.profile_write)]
+            .into_iter()
+            .filter(|(_, checked)| checked.is_some())
+            .map(|(scope, _)| scope.to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+
+use crate::tokens::models::{ApiToken, NewApiToken};
+use crate::utils::db::QueryError;
+
+/// Storage of personal access tokens.
+#[async_trait]
+pub trait TokenRepository: Send + Sync {
+    /// Store a new token, returning its id.
+    async fn create(&self, token: NewApiToken) -> Result<i32, QueryError>;
+
+    /// A user's tokens, newest first.
+    async fn list(&self, user_id: i32) -> Result<Vec<ApiToken>, QueryError>;
+
+    /// A token by its lookup prefix.
+    async fn find_by_prefix(&self, prefix: &str) -> Result<ApiToken, QueryError>;
+
+    /// Record the use of a token; repeated uses from the same address within
+    /// a minute are not written again.
+    async fn touch(&self, id: i32, ip: Option<&str>) -> Result<(), QueryError>;
+
+    /// Revoke one of a user's tokens; returns false when there was nothing to revoke.
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<bool, QueryError>;
+}
@@ -1,8 +1,4 @@
-use once_cell::sync::Lazy;
-
-static MAX_AGE_COOKIE: Lazy<String> = Lazy::new(|| {
-    dotenv::var("MAX_AGE_COOKIE").expect("DATABASE_URL must be set")
-});
+use crate::utils::jwt::session_hours;
 
 pub mod option_date {
     use chrono::NaiveDate;
@@ -31,9 +27,6 @@ pub mod option_date {
 }
 
 pub fn get_max_age_seconds() -> String {
-    let max_age: i64 = MAX_AGE_COOKIE
-        .parse()
-        .expect("MAX_AGE_COOKIE must be an integer");
-    let max_age_seconds = 3600 * max_age;
+    let max_age_seconds = 3600 * session_hours();
     max_age_seconds.to_string()
 }
\ No newline at end of file
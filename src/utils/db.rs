@@ -1,22 +1,37 @@
-use async_trait::async_trait;
-use sqlx::{Error as SqlxError, PgPool, postgres::PgRow};
-
-use crate::profile::models::{NewUser, PasswordChange, UpdateUserEmailVerify};
+use thiserror::Error;
+use sqlx::error::ErrorKind;
+use sqlx::Error as SqlxError;
 
 /// Enum representing various database query errors.
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum QueryError {
-    Duplicate,
+    #[error("duplicate record (constraint {constraint:?})")]
+    Duplicate { constraint: Option<String> },
+    #[error("foreign key violation (constraint {constraint:?})")]
+    ForeignKey { constraint: Option<String> },
+    #[error("check constraint violation (constraint {constraint:?})")]
+    CheckViolation { constraint: Option<String> },
+    #[error("record not found")]
     NotFound,
+    #[error("database error: {0}")]
     DatabaseError(SqlxError),
+    #[error("row not found")]
     RowNotFound,
+    #[error("query error")]
     Query,
+    #[error("type not found")]
     TypeNotFound,
+    #[error("connection closed")]
     ConnectionClosed,
+    #[error("pool timed out")]
     PoolTimedOut,
+    #[error("tls error")]
     Tls,
+    #[error("protocol error")]
     Protocol,
+    #[error("invalid query")]
     InvalidQuery,
+    #[error("io error")]
     Io,
 }
 
@@ -26,97 +41,60 @@ impl From<SqlxError> for QueryError {
         match err {
             SqlxError::RowNotFound => QueryError::RowNotFound,
             SqlxError::PoolTimedOut => QueryError::PoolTimedOut,
+            SqlxError::Database(ref db_err) => {
+                let constraint = db_err
+                    .constraint()
+                    .map(|name| name.to_string())
+                    .or_else(|| sqlite_constraint(db_err.message()));
+                match db_err.kind() {
+                    ErrorKind::UniqueViolation => QueryError::Duplicate { constraint },
+                    ErrorKind::ForeignKeyViolation => QueryError::ForeignKey { constraint },
+                    ErrorKind::CheckViolation => QueryError::CheckViolation { constraint },
+                    _ => QueryError::DatabaseError(err),
+                }
+            }
             _ => QueryError::DatabaseError(err),
         }
     }
 }
 
-/// Trait defining database utility methods.
-#[async_trait]
-pub trait DatabaseUtils {
-    async fn find_record(&self, query: &str, param: &str) -> Result<bool, QueryError>;
-    async fn select_existence(&self, query: &str, param: &str) -> Result<PgRow, QueryError>;
-}
-
-#[async_trait]
-impl DatabaseUtils for PgPool {
-    async fn find_record(&self, query: &str, param: &str) -> Result<bool, QueryError> {
-        let exists: (bool,) = sqlx::query_as(query)
-            .bind(param)
-            .fetch_one(self)
-            .await
-            .map_err(QueryError::from)?;
-        Ok(exists.0)
+/// SQLite does not name the violated constraint, only its columns, as in
+/// `UNIQUE constraint failed: users.email`; that becomes `users_email_key`,
+/// the name Postgres gives the same constraint.
+fn sqlite_constraint(message: &str) -> Option<String> {
+    let columns = message.strip_prefix("UNIQUE constraint failed: ")?;
+    // unique indexes on expressions are reported by name
+    if let Some(index) = columns.strip_prefix("index '") {
+        return index.strip_suffix('\'').map(str::to_string);
     }
-
-    async fn select_existence(&self, query: &str, param: &str) -> Result<PgRow, QueryError> {
-        let row = sqlx::query(query)
-            .bind(param)
-            .fetch_one(self)
-            .await
-            .map_err(QueryError::from)?;
-        Ok(row)
+    let mut names = columns.split(", ").filter_map(|column| column.split_once('.'));
+    let (table, column) = names.next()?;
+    let mut name = format!("{}_{}", table, column);
+    for (_, column) in names {
+        name.push('_');
+        name.push_str(column);
     }
+    name.push_str("_key");
+    Some(name)
 }
 
-/// Check if the email exists in the database.
-pub async fn check_email(state: &PgPool, email: String) -> Result<bool, QueryError> {
-    let query = "SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)";
-    state.find_record(query, &email).await
-}
-
-/// Check if the username exists in the database.
-pub async fn check_username(state: &PgPool, username: String) -> Result<bool, QueryError> {
-    let query = "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)";
-    state.find_record(query, &username).await
-}
-
-/// Retrieve a user by email from the database.
-pub async fn get_user(state: &PgPool, email: String) -> Result<PgRow, QueryError> {
-    let query = "SELECT * FROM users WHERE email = $1";
-    state.select_existence(query, &email).await
-}
-
-/// Insert a new user into the database.
-pub async fn query_new_user(state: &PgPool, user: NewUser) -> Result<(), QueryError> {
-    let query = "
-        INSERT INTO users (email, username, password, is_verify, created_at)
-        VALUES ($1, $2, $3, $4, $5)
-    ";
-    sqlx::query(query)
-        .bind(&user.email)
-        .bind(&user.username)
-        .bind(&user.password)
-        .bind(user.is_verify)
-        .bind(user.created_at)
-        .execute(state)
-        .await
-        .map_err(QueryError::from)?;
-    Ok(())
-}
-
-/// Update a user's email verification status.
-pub async fn query_update_user(state: &PgPool, user: UpdateUserEmailVerify) -> Result<(), QueryError> {
-    let query = "UPDATE users SET is_verify = $2, updated_at = $3 WHERE email = $1";
-    sqlx::query(query)
-        .bind(&user.email)
-        .bind(user.is_verify)
-        .bind(user.updated_at)
-        .execute(state)
-        .await
-        .map_err(QueryError::from)?;
-    Ok(())
-}
+impl QueryError {
+    /// Name of the violated constraint, for constraint violations.
+    pub fn constraint(&self) -> Option<&str> {
+        match self {
+            QueryError::Duplicate { constraint }
+            | QueryError::ForeignKey { constraint }
+            | QueryError::CheckViolation { constraint } => constraint.as_deref(),
+            _ => None,
+        }
+    }
 
-/// Update a user's password.
-pub async fn query_update_password(state: &PgPool, user: PasswordChange) -> Result<(), QueryError> {
-    let query = "UPDATE users SET password = $2, updated_at = $3 WHERE email = $1";
-    sqlx::query(query)
-        .bind(&user.email)
-        .bind(&user.password)
-        .bind(user.updated_at)
-        .execute(state)
-        .await
-        .map_err(QueryError::from)?;
-    Ok(())
+    /// The form field a constraint violation on `users` refers to.
+    pub fn user_field(&self) -> Option<&'static str> {
+        match self.constraint()? {
+            "users_email_key" | "users_email_lower_key" => Some("email"),
+            "users_username_key" | "users_username_lower_key" => Some("username"),
+            _ => None,
+        }
+    }
 }
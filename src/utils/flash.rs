@@ -0,0 +1,156 @@
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::extract::{FromRequestParts, Request};
+use axum::http::header::{COOKIE, SET_COOKIE};
+use axum::http::request::Parts;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use cookie::{Cookie, SameSite};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+
+use crate::config;
+use crate::utils::cookie::extract_cookie_value;
+use crate::utils::message::Message;
+
+const FLASH_COOKIE: &str = "flash";
+/// Messages beyond this are dropped to keep the cookie small.
+const MAX_MESSAGES: usize = 8;
+
+static FLASH_KEY: Lazy<Vec<u8>> = Lazy::new(|| config::get().jwt_secret.expose().as_bytes().to_vec());
+
+#[derive(Default)]
+struct Store {
+    /// Messages read from the cookie of this request.
+    incoming: Vec<Message>,
+    /// Messages queued while handling this request.
+    outgoing: Vec<Message>,
+    /// Whether a rendered page has taken the messages.
+    shown: bool,
+}
+
+/// Messages for the next page the user sees, usually after a redirect.
+///
+/// Handlers queue messages and return as usual; `carry_flash` stores them
+/// in a signed cookie and the next rendered template shows them in
+/// `messages`.
+#[derive(Clone, Default)]
+pub struct Flash(Arc<Mutex<Store>>);
+
+impl Flash {
+    pub fn push(&self, tags: &str, content: impl Into<String>) {
+        let mut store = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if store.incoming.len() + store.outgoing.len() < MAX_MESSAGES {
+            store.outgoing.push(Message {
+                content: content.into(),
+                tags: tags.to_string(),
+            });
+        }
+    }
+
+    pub fn success(&self, content: impl Into<String>) {
+        self.push("success", content)
+    }
+
+    pub fn info(&self, content: impl Into<String>) {
+        self.push("info", content)
+    }
+
+    pub fn error(&self, content: impl Into<String>) {
+        self.push("danger", content)
+    }
+
+    /// Every pending message, for the page being rendered.
+    pub fn take(&self) -> Vec<Message> {
+        let mut store = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        store.shown = true;
+        let mut messages = std::mem::take(&mut store.incoming);
+        messages.append(&mut store.outgoing);
+        messages
+    }
+}
+
+// the store is created once per request by `carry_flash`
+#[async_trait]
+impl<S> FromRequestParts<S> for Flash
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Flash>().cloned().unwrap_or_default())
+    }
+}
+
+fn mac(payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&FLASH_KEY).expect("HMAC accepts any key length");
+    mac.update(format!("flash:{}", payload).as_bytes());
+    mac
+}
+
+fn encode(messages: &[Message]) -> String {
+    let payload = hex::encode(serde_json::to_vec(messages).unwrap_or_default());
+    let signature = hex::encode(mac(&payload).finalize().into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+fn decode(value: &str) -> Option<Vec<Message>> {
+    let (payload, signature) = value.split_once('.')?;
+    mac(payload).verify_slice(&hex::decode(signature).ok()?).ok()?;
+    serde_json::from_slice(&hex::decode(payload).ok()?).ok()
+}
+
+fn flash_cookie(value: String) -> Cookie<'static> {
+    Cookie::build((FLASH_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// Load flash messages from the cookie and write back whatever is still
+/// pending once the handler has run.
+///
+/// Responses that neither render a page nor queue anything leave the cookie
+/// alone, so assets fetched alongside a page cannot bring messages back.
+pub async fn carry_flash(mut request: Request, next: Next) -> Response {
+    let cookie = request
+        .headers()
+        .get(COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|cookies| extract_cookie_value(cookies, FLASH_COOKIE));
+    let flash = Flash::default();
+    if let Some(messages) = cookie.as_deref().and_then(decode) {
+        flash.0.lock().unwrap_or_else(|e| e.into_inner()).incoming = messages;
+    }
+    request.extensions_mut().insert(flash.clone());
+
+    let mut response = next.run(request).await;
+
+    let mut store = flash.0.lock().unwrap_or_else(|e| e.into_inner());
+    if !store.shown && store.outgoing.is_empty() {
+        return response;
+    }
+    let mut pending = std::mem::take(&mut store.incoming);
+    pending.append(&mut store.outgoing);
+
+    let cookie = if !pending.is_empty() {
+        flash_cookie(encode(&pending))
+    } else if cookie.is_some() {
+        let mut cookie = flash_cookie(String::new());
+        cookie.make_removal();
+        cookie
+    } else {
+        return response;
+    };
+    if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+        response.headers_mut().append(SET_COOKIE, value);
+    }
+    response
+}
@@ -3,16 +3,17 @@ use argon2::{Argon2, password_hash::{
     PasswordHasher, PasswordVerifier, rand_core::OsRng, SaltString},
 };
 use argon2::password_hash::Error;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, DecodingKey, encode, EncodingKey, Header};
 use once_cell::sync::Lazy;
 use thiserror::Error;
 
 use crate::auth::models::Claims;
+use crate::config;
+use crate::metrics;
 
 static KEYS: Lazy<Keys> = Lazy::new(|| {
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    Keys::new(secret.as_bytes())
+    Keys::new(config::get().jwt_secret.expose().as_bytes())
 });
 
 struct Keys {
@@ -30,7 +31,30 @@ impl Keys {
 }
 
 pub async fn encode_jwt(email: String, purpose: String, duration: i64) -> Result<String, String> {
-    let now = Utc::now();
+    encode_jwt_at(email, purpose, Utc::now(), duration)
+}
+
+/// Lifetime of a session token in hours, the same as the cookie holding it.
+pub fn session_hours() -> i64 {
+    config::get().max_age_cookie
+}
+
+/// Issue a session token that lasts `session_hours()`.
+pub async fn encode_session(email: String) -> Result<String, String> {
+    encode_jwt_at(email, "auth".to_string(), Utc::now(), session_hours())
+}
+
+/// Issue a session token that outlives a revocation of the user's sessions
+/// at `revoked_at`.
+///
+/// `iat` only has whole seconds, and a revocation ends every token issued up
+/// to and including its second, so the token is dated the second after.
+pub async fn encode_session_after(email: String, revoked_at: DateTime<Utc>) -> Result<String, String> {
+    let issued_at = DateTime::from_timestamp(revoked_at.timestamp() + 1, 0).unwrap_or(revoked_at);
+    encode_jwt_at(email, "auth".to_string(), issued_at.max(Utc::now()), session_hours())
+}
+
+fn encode_jwt_at(email: String, purpose: String, now: DateTime<Utc>, duration: i64) -> Result<String, String> {
     let exp = (now + Duration::hours(duration)).timestamp() as usize;
     let iat = now.timestamp() as usize;
 
@@ -39,6 +63,55 @@ pub async fn encode_jwt(email: String, purpose: String, duration: i64) -> Result
         exp,
         email,
         purpose,
+        user_id: None,
+        impersonator_id: None,
+    };
+
+    encode(
+        &Header::default(),
+        &claim,
+        &KEYS.encoding,
+    ).map_err(|err| err.to_string())
+}
+
+/// Issue a session token that authenticates as `user_id`/`email` on behalf of
+/// the admin `impersonator_id`.
+pub async fn encode_impersonation_jwt(
+    user_id: i32,
+    email: String,
+    impersonator_id: i32,
+    duration: i64,
+) -> Result<String, String> {
+    let now = Utc::now();
+    let exp = (now + Duration::hours(duration)).timestamp() as usize;
+    let iat = now.timestamp() as usize;
+
+    let claim = Claims {
+        iat,
+        exp,
+        email,
+        purpose: "impersonate".to_string(),
+        user_id: Some(user_id),
+        impersonator_id: Some(impersonator_id),
+    };
+
+    encode(
+        &Header::default(),
+        &claim,
+        &KEYS.encoding,
+    ).map_err(|err| err.to_string())
+}
+
+/// Issue the token of a link that moves user `user_id` to the address `email`.
+pub async fn encode_email_change_jwt(user_id: i32, email: String, duration: i64) -> Result<String, String> {
+    let now = Utc::now();
+    let claim = Claims {
+        iat: now.timestamp() as usize,
+        exp: (now + Duration::hours(duration)).timestamp() as usize,
+        email,
+        purpose: "email-change".to_string(),
+        user_id: Some(user_id),
+        impersonator_id: None,
     };
 
     encode(
@@ -60,7 +133,7 @@ pub enum DecodeTokenError {
 
 pub async fn decode_token(token: String) -> Result<Option<Claims>, DecodeTokenError> {
     let decoded = decode::<Claims>(
-        &*token,
+        &token,
         &KEYS.decoding,
         &jsonwebtoken::Validation::default(),
     );
@@ -82,16 +155,31 @@ pub async fn decode_token(token: String) -> Result<Option<Claims>, DecodeTokenEr
 pub fn ar_hash_password(password: &str) -> Result<String, Error> {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
-    match argon2.hash_password(password.as_bytes(), &salt) {
+    match metrics::time_password_hashing("hash", || argon2.hash_password(password.as_bytes(), &salt)) {
         Ok(hash) => Ok(hash.to_string()),
         Err(e) => Err(e),
     }
 }
 
+/// A valid hash of a throwaway password, verified against when the account
+/// does not exist so that misses cost the same as wrong passwords.
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(b"dummy password", &salt)
+        .expect("hashing a constant password cannot fail")
+        .to_string()
+});
+
+/// Spend the same work as `ar_verify_password` for an unknown account.
+pub fn ar_dummy_verify(password: &str) {
+    let _ = ar_verify_password(password, &DUMMY_HASH);
+}
+
 pub fn ar_verify_password(password: &str, hashed_password: &str) -> Result<bool, Error> {
     let parsed_hash = PasswordHash::new(hashed_password)?;
     let argon2 = Argon2::default();
-    match argon2.verify_password(password.as_bytes(), &parsed_hash) {
+    match metrics::time_password_hashing("verify", || argon2.verify_password(password.as_bytes(), &parsed_hash)) {
         Ok(_) => Ok(true),
         Err(e) => Err(e)
     }
@@ -0,0 +1,134 @@
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use once_cell::sync::Lazy;
+use tracing::{info, warn};
+
+use crate::config;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Where outgoing mail goes, chosen by the configuration.
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// `.eml` files in a directory, for development.
+    Outbox(AsyncFileTransport<Tokio1Executor>),
+}
+
+static TRANSPORT: Lazy<Option<Transport>> = Lazy::new(|| {
+    let config = config::get();
+    if let Some(url) = &config.smtp_url {
+        let builder = AsyncSmtpTransport::<Tokio1Executor>::from_url(url.expose())
+            .expect("smtp_url is checked when the configuration loads");
+        Some(Transport::Smtp(builder.build()))
+    } else {
+        config.mail_outbox.as_ref().map(|dir| Transport::Outbox(AsyncFileTransport::new(dir)))
+    }
+});
+
+/// Absolute link to a path on this site, for use in emails.
+pub fn site_url(path: &str) -> String {
+    format!("{}{}", config::get().public_url, path)
+}
+
+/// Queue a message for delivery.
+///
+/// Delivery happens off the request path so that response times do not
+/// depend on whether a message was sent. Bodies carry tokens, so only the
+/// recipient and subject are ever logged.
+pub fn send(mail: Mail) {
+    tokio::spawn(async move {
+        match deliver(&mail).await {
+            Ok(true) => info!(target: "mail", to = %mail.to, subject = %mail.subject, "mail sent"),
+            Ok(false) => warn!(target: "mail", to = %mail.to, subject = %mail.subject, "no mail transport is configured, mail dropped"),
+            Err(err) => warn!(target: "mail", to = %mail.to, subject = %mail.subject, "mail not sent: {}", err),
+        }
+    });
+}
+
+/// Hand `mail` to the transport; false when there is none.
+async fn deliver(mail: &Mail) -> Result<bool, BoxError> {
+    let Some(transport) = TRANSPORT.as_ref() else {
+        return Ok(false);
+    };
+    let message = Message::builder()
+        .from(config::get().mail_from.parse::<Mailbox>()?)
+        .to(mail.to.parse::<Mailbox>()?)
+        .subject(&mail.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(mail.body.clone())?;
+    match transport {
+        Transport::Smtp(smtp) => {
+            smtp.send(message).await?;
+        }
+        Transport::Outbox(outbox) => {
+            outbox.send(message).await?;
+        }
+    }
+    Ok(true)
+}
+
+pub fn verify_email(to: &str, token: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Verify your email".to_string(),
+        body: format!(
+            "Go to email-verify: {}",
+            site_url(&format!("/account/email-verify?token={}", token))
+        ),
+    }
+}
+
+pub fn reset_password(to: &str, token: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Go to change password: {}",
+            site_url(&format!("/account/reset-password-confirm?token={}", token))
+        ),
+    }
+}
+
+pub fn confirm_email_change(to: &str, token: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Confirm your new email".to_string(),
+        body: format!(
+            "Go to confirm this address for your account: {}",
+            site_url(&format!("/account/email-verify?token={}", token))
+        ),
+    }
+}
+
+pub fn email_change_taken(to: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Email change attempt".to_string(),
+        body: format!(
+            "Someone tried to move their account to this address, but it already has an account.\n\
+             Nothing has changed. If you are worried, you can reset your password at {}",
+            site_url("/account/reset-password"),
+        ),
+    }
+}
+
+pub fn account_exists(to: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Sign up attempt".to_string(),
+        body: format!(
+            "Someone tried to sign up with this address, but it already has an account.\n\
+             If that was you, log in at {} or reset your password at {}",
+            site_url("/account/login"),
+            site_url("/account/reset-password"),
+        ),
+    }
+}
@@ -1,22 +1,32 @@
-use serde::Serialize;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 use validator::ValidationErrors;
 
-#[derive(Serialize)]
+use crate::i18n::Locale;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub content: String,
     pub tags: String,
 }
 
-pub async fn handle_errors(errors: ValidationErrors) -> Vec<Message> {
+/// The first error of every invalid field, translated from its validator code.
+pub async fn handle_errors(errors: ValidationErrors, locale: Locale) -> Vec<Message> {
     let mut messages = Vec::new();
-    for error in errors.field_errors() {
-        if let Some(message) = error.1.get(0).and_then(|m| m.message.as_ref()) {
+    for (_, field_messages) in locale.validation_messages(&errors) {
+        if let Some(message) = field_messages.into_iter().next() {
             messages.push(Message {
-                content: message.to_string(),
+                content: message,
                 tags: "danger".to_string(), // Example tag
             });
         }
     }
-    return messages;
+    messages
 }
 
+
+/// A message attached to a single form field, rendered next to its input.
+pub fn field_error(field: &str, message: &str) -> HashMap<String, String> {
+    HashMap::from([(field.to_string(), message.to_string())])
+}
@@ -0,0 +1,104 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use tracing::info;
+
+use crate::storage::database::Database;
+
+/// The migrations in `migrations/postgres/`, embedded at build time.
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// The migrations in `migrations/sqlite/`, embedded at build time.
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// The migrations for the database's backend; none for `Memory`.
+pub fn migrator(db: &Database) -> Option<&'static Migrator> {
+    match db {
+        Database::Postgres(_) => Some(&POSTGRES_MIGRATOR),
+        Database::Sqlite(_) => Some(&SQLITE_MIGRATOR),
+        Database::Memory => None,
+    }
+}
+
+async fn applied_on(conn: &mut impl Migrate) -> Result<Vec<i64>, MigrateError> {
+    conn.ensure_migrations_table().await?;
+    let mut versions: Vec<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    versions.sort();
+    Ok(versions)
+}
+
+fn not_applied(migrator: &Migrator, applied: &[i64]) -> Vec<String> {
+    migrator
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration() && !applied.contains(&m.version))
+        .map(|m| format!("{} {}", m.version, m.description))
+        .collect()
+}
+
+/// Versions of the migrations applied to the database, oldest first.
+pub async fn applied_versions(db: &Database) -> Result<Vec<i64>, MigrateError> {
+    match db {
+        Database::Postgres(pool) => applied_on(&mut *pool.acquire().await?).await,
+        Database::Sqlite(pool) => applied_on(&mut *pool.acquire().await?).await,
+        Database::Memory => Ok(Vec::new()),
+    }
+}
+
+/// `version description` of every embedded migration not yet applied.
+pub async fn pending(db: &Database) -> Result<Vec<String>, MigrateError> {
+    let applied = applied_versions(db).await?;
+    Ok(migrator(db).map_or_else(Vec::new, |migrator| not_applied(migrator, &applied)))
+}
+
+/// Apply every pending migration.
+pub async fn run(db: &Database) -> Result<(), MigrateError> {
+    match db {
+        Database::Postgres(pool) => POSTGRES_MIGRATOR.run(pool).await,
+        Database::Sqlite(pool) => SQLITE_MIGRATOR.run(pool).await,
+        Database::Memory => Ok(()),
+    }
+}
+
+/// Revert every migration after `target`.
+pub async fn undo(db: &Database, target: i64) -> Result<(), MigrateError> {
+    match db {
+        Database::Postgres(pool) => POSTGRES_MIGRATOR.undo(pool, target).await,
+        Database::Sqlite(pool) => SQLITE_MIGRATOR.undo(pool, target).await,
+        Database::Memory => Ok(()),
+    }
+}
+
+/// Apply pending migrations, or with `apply` false only report them.
+///
+/// Runs under the same lock as `Migrator::run` (an advisory lock on
+/// Postgres), so instances starting together neither race each other nor
+/// `migrate up`.
+pub async fn on_startup(db: &Database, apply: bool) -> Result<Vec<String>, MigrateError> {
+    match db {
+        Database::Postgres(pool) => startup_on(&mut *pool.acquire().await?, &POSTGRES_MIGRATOR, apply).await,
+        Database::Sqlite(pool) => startup_on(&mut *pool.acquire().await?, &SQLITE_MIGRATOR, apply).await,
+        Database::Memory => Ok(Vec::new()),
+    }
+}
+
+async fn startup_on(conn: &mut impl Migrate, migrator: &Migrator, apply: bool) -> Result<Vec<String>, MigrateError> {
+    conn.lock().await?;
+
+    let result = async {
+        let pending = not_applied(migrator, &applied_on(conn).await?);
+        if apply && !pending.is_empty() {
+            info!("Applying {} migration(s): {}", pending.len(), pending.join(", "));
+            // the lock is re-entrant for this session
+            migrator.run_direct(conn).await?;
+            return Ok(Vec::new());
+        }
+        Ok(pending)
+    }
+    .await;
+
+    conn.unlock().await?;
+    result
+}
@@ -0,0 +1,253 @@
+//! The JSON API end to end, on in-memory repositories.
+
+use std::collections::HashMap;
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::{Request, StatusCode};
+use axum::middleware::from_fn_with_state;
+use axum::response::Response;
+use axum::{Extension, Router};
+use chrono::Utc;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use axum_example::audit::models::AuditFilter;
+use axum_example::auth::middleware::cookie_to_state;
+use axum_example::common::{RequestMeta, Templates};
+use axum_example::config::{self, Config};
+use axum_example::i18n::Locale;
+use axum_example::profile::handlers::get_verify_email;
+use axum_example::profile::models::UpdateUserEmailVerify;
+use axum_example::routes_api;
+use axum_example::state::AppState;
+use axum_example::utils::flash::Flash;
+use axum_example::utils::jwt::{encode_email_change_jwt, encode_impersonation_jwt, encode_jwt, encode_session_after};
+
+fn state() -> AppState {
+    let config = config::init(Config::default());
+    AppState::in_memory(config).unwrap()
+}
+
+fn router(state: &AppState) -> Router {
+    routes_api::build_routes(state.clone()).layer(from_fn_with_state(state.clone(), cookie_to_state))
+}
+
+async fn send(state: &AppState, method: &str, path: &str, token: Option<&str>, body: Value) -> Response {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+    router(state).oneshot(request).await.unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+async fn signup(state: &AppState, email: &str, username: &str) -> StatusCode {
+    let body = json!({ "email": email, "username": username, "password": "password123" });
+    send(state, "POST", "/api/v1/signup", None, body).await.status()
+}
+
+async fn login(state: &AppState, email: &str, password: &str) -> Response {
+    let body = json!({ "email": email, "password": password });
+    send(state, "POST", "/api/v1/login", None, body).await
+}
+
+async fn verify(state: &AppState, email: &str) {
+    let update = UpdateUserEmailVerify {
+        is_verify: true,
+        updated_at: Some(Utc::now()),
+        email: email.to_string(),
+    };
+    state.users.set_verified(update).await.unwrap();
+}
+
+#[tokio::test]
+async fn signup_login_and_read_profile() {
+    let state = state();
+    assert_eq!(signup(&state, "ann@example.com", "ann").await, StatusCode::ACCEPTED);
+    assert_eq!(login(&state, "ann@example.com", "password123").await.status(), StatusCode::FORBIDDEN);
+
+    verify(&state, "ann@example.com").await;
+    let response = login(&state, "ann@example.com", "password123").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = json_body(response).await["token"].as_str().unwrap().to_string();
+
+    let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let profile = json_body(response).await;
+    assert_eq!(profile["username"], "ann");
+    assert_eq!(profile["is_verify"], true);
+
+    let events = state.audit.events(&AuditFilter::default(), 10).await.unwrap();
+    let names: Vec<&str> = events.iter().map(|event| event.event.as_str()).collect();
+    assert_eq!(names, ["login.success", "signup"]);
+}
+
+#[tokio::test]
+async fn wrong_password_and_unknown_email_look_alike() {
+    let state = state();
+    signup(&state, "bob@example.com", "bob").await;
+    verify(&state, "bob@example.com").await;
+
+    let wrong = login(&state, "bob@example.com", "password124").await;
+    let unknown = login(&state, "nobody@example.com", "password123").await;
+    assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json_body(wrong).await, json_body(unknown).await);
+}
+
+#[tokio::test]
+async fn login_by_username_or_email_ignores_case() {
+    let state = state();
+    signup(&state, "Dan@example.com", "Dan").await;
+    verify(&state, "Dan@example.com").await;
+
+    for login_name in ["Dan", "dan", "dan@EXAMPLE.com"] {
+        let body = json!({ "login": login_name, "password": "password123" });
+        let response = send(&state, "POST", "/api/v1/login", None, body).await;
+        assert_eq!(response.status(), StatusCode::OK, "{login_name}");
+    }
+    // a differently cased username is the same name
+    assert_eq!(signup(&state, "other@example.com", "DAN").await, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn taken_username_is_a_field_error() {
+    let state = state();
+    assert_eq!(signup(&state, "cat@example.com", "cat").await, StatusCode::ACCEPTED);
+    assert_eq!(signup(&state, "other@example.com", "cat").await, StatusCode::UNPROCESSABLE_ENTITY);
+    // a taken email is not revealed
+    assert_eq!(signup(&state, "cat@example.com", "kitten").await, StatusCode::ACCEPTED);
+    assert_eq!(state.users.list().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn usernames_are_unique_in_any_case_and_not_emails() {
+    let state = state();
+    assert_eq!(signup(&state, "cat@example.com", "cat").await, StatusCode::ACCEPTED);
+    assert_eq!(signup(&state, "other@example.com", "CAT").await, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(signup(&state, "dog@example.com", "dog@example.com").await, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(state.users.list().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn impersonation_tokens_are_refused_as_bearer_credentials() {
+    let state = state();
+    for (email, username) in [("root@example.com", "root"), ("ann@example.com", "ann")] {
+        signup(&state, email, username).await;
+        verify(&state, email).await;
+    }
+    state.users.set_admin("root@example.com", true).await.unwrap();
+    let admin = state.users.find_by_email("root@example.com").await.unwrap();
+    let target = state.users.find_by_email("ann@example.com").await.unwrap();
+    let token = encode_impersonation_jwt(target.id, target.email, admin.id, 1).await.unwrap();
+
+    let body = json!({ "email": "mallory@example.com", "username": "mallory" });
+    let response = send(&state, "PUT", "/api/v1/profile", Some(&token), body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(state.users.find_by_id(target.id).await.unwrap().username, "ann");
+
+    let filter = AuditFilter { event: Some("impersonation.blocked".to_string()), ..Default::default() };
+    let events = state.audit.events(&filter, 10).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].actor_id, Some(admin.id));
+}
+
+async fn session(state: &AppState, email: &str) -> String {
+    let response = login(state, email, "password123").await;
+    json_body(response).await["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn password_reset_ends_every_session() {
+    let state = state();
+    signup(&state, "ann@example.com", "ann").await;
+    verify(&state, "ann@example.com").await;
+    let token = session(&state, "ann@example.com").await;
+
+    let reset = encode_jwt("ann@example.com".to_string(), "reset-password".to_string(), 1).await.unwrap();
+    let body = json!({ "token": reset, "password": "password456" });
+    let response = send(&state, "POST", "/api/v1/reset-password-confirm", None, body).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn session_issued_after_a_revocation_survives_it() {
+    let state = state();
+    signup(&state, "ann@example.com", "ann").await;
+    verify(&state, "ann@example.com").await;
+    let other = session(&state, "ann@example.com").await;
+
+    state.sessions.revoke_all("ann@example.com").await.unwrap();
+    let revoked_at = state.users.find_by_email("ann@example.com").await.unwrap().sessions_revoked_at.unwrap();
+    let kept = encode_session_after("ann@example.com".to_string(), revoked_at).await.unwrap();
+
+    for (token, status) in [(other, StatusCode::UNAUTHORIZED), (kept, StatusCode::OK)] {
+        let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+        assert_eq!(response.status(), status);
+    }
+}
+
+#[tokio::test]
+async fn audit_chain_of_memory_store_verifies() {
+    let state = state();
+    for n in 0..3 {
+        signup(&state, &format!("user{n}@example.com"), &format!("user{n}")).await;
+    }
+    let report = axum_example::audit::chain::verify(state.audit.as_ref()).await.unwrap();
+    assert!(report.is_intact());
+    assert_eq!(report.events_checked, 3);
+}
+
+#[tokio::test]
+async fn email_change_does_not_reveal_taken_addresses() {
+    let state = state();
+    for (email, username) in [("ann@example.com", "ann"), ("bob@example.com", "bob")] {
+        signup(&state, email, username).await;
+        verify(&state, email).await;
+    }
+    let token = session(&state, "ann@example.com").await;
+
+    let mut bodies = Vec::new();
+    for email in ["bob@example.com", "new@example.com"] {
+        let body = json!({ "email": email, "username": "ann" });
+        let response = send(&state, "PUT", "/api/v1/profile", Some(&token), body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        bodies.push(json_body(response).await);
+        // nothing changes until the new address confirms, so the session lives on
+        let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(bodies[0], bodies[1]);
+    assert_eq!(bodies[0]["email"], "ann@example.com");
+
+    let ann = state.users.find_by_email("ann@example.com").await.unwrap();
+    let link = encode_email_change_jwt(ann.id, "new@example.com".to_string(), 1).await.unwrap();
+    let templates = Templates::new(state.templates.clone(), Locale::parse("en").unwrap(), Flash::default(), Default::default());
+    let params = HashMap::from([("token".to_string(), link)]);
+    get_verify_email(State(state.clone()), Extension(templates), Flash::default(), RequestMeta::default(), Query(params))
+        .await
+        .unwrap();
+
+    let moved = state.users.find_by_id(ann.id).await.unwrap();
+    assert_eq!(moved.email, "new@example.com");
+    assert!(moved.is_verify);
+    let response = send(&state, "GET", "/api/v1/profile", Some(&token), Value::Null).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // an address taken since the link was sent stays with its account
+    assert!(state.users.change_email(ann.id, "bob@example.com").await.is_err());
+}
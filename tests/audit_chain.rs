@@ -0,0 +1,172 @@
+//! Tampering with the audit chain must make `chain::verify` fail.
+
+use async_trait::async_trait;
+
+use axum_example::audit::chain::{self, CHECKPOINT_INTERVAL, VerifyReport};
+use axum_example::audit::models::{AuditCheckpoint, AuditEvent, AuditFilter, NewAuditEvent};
+use axum_example::audit::repository::AuditRepository;
+use axum_example::common::RequestMeta;
+use axum_example::config::{self, Config};
+use axum_example::storage::memory::MemoryStore;
+use axum_example::utils::db::QueryError;
+
+/// Rows as they sit in the database, open to edits that the stores forbid.
+struct Rows {
+    events: Vec<AuditEvent>,
+    checkpoints: Vec<AuditCheckpoint>,
+}
+
+#[async_trait]
+impl AuditRepository for Rows {
+    async fn append(&self, _event: &NewAuditEvent) -> Result<i64, QueryError> {
+        unimplemented!("rows are written by the memory store")
+    }
+
+    async fn chain(&self, after_id: i64, limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        let events = self.events.iter().filter(|event| event.id > after_id);
+        Ok(events.take(limit as usize).cloned().collect())
+    }
+
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, QueryError> {
+        Ok(self.checkpoints.clone())
+    }
+
+    async fn events(&self, _filter: &AuditFilter, _limit: i64) -> Result<Vec<AuditEvent>, QueryError> {
+        unimplemented!("not used by verify")
+    }
+}
+
+/// A chain of `count` events written by the memory store.
+async fn rows(count: i64) -> Rows {
+    config::init(Config::default());
+    let store = MemoryStore::new();
+    for n in 0..count {
+        let event = NewAuditEvent::new("login", &RequestMeta::default()).actor(n as i32);
+        store.append(&event).await.unwrap();
+    }
+    Rows {
+        events: store.chain(0, count).await.unwrap(),
+        checkpoints: store.checkpoints().await.unwrap(),
+    }
+}
+
+async fn verify(rows: &Rows) -> VerifyReport {
+    chain::verify(rows).await.unwrap()
+}
+
+fn broken_at(report: &VerifyReport) -> i64 {
+    report.first_broken.as_ref().expect("chain should be broken").event_id
+}
+
+#[tokio::test]
+async fn untouched_chain_verifies() {
+    let rows = rows(2 * CHECKPOINT_INTERVAL + 5).await;
+    let report = verify(&rows).await;
+    assert!(report.is_intact(), "{:?}", report.first_broken);
+    assert_eq!(report.events_checked, 205);
+    assert_eq!(report.checkpoints_checked, 2);
+}
+
+#[tokio::test]
+async fn ids_may_skip_values() {
+    // a rolled back insert leaves a gap in the id sequence but not in positions
+    let mut rows = rows(CHECKPOINT_INTERVAL + 5).await;
+    for event in &mut rows.events[50..] {
+        event.id += 1;
+    }
+    for checkpoint in &mut rows.checkpoints {
+        checkpoint.event_id += 1;
+        checkpoint.signature = chain::checkpoint_signature(checkpoint.event_id, &checkpoint.hash);
+    }
+    assert!(verify(&rows).await.is_intact());
+}
+
+#[tokio::test]
+async fn edited_event_is_detected() {
+    let mut rows = rows(10).await;
+    rows.events[4].details = Some("{\"forged\":true}".to_string());
+    assert_eq!(broken_at(&verify(&rows).await), 5);
+}
+
+#[tokio::test]
+async fn deleted_event_is_detected() {
+    let mut rows = rows(10).await;
+    rows.events.remove(4);
+    assert_eq!(broken_at(&verify(&rows).await), 6);
+}
+
+#[tokio::test]
+async fn truncated_head_is_detected() {
+    let mut rows = rows(10).await;
+    rows.events.drain(..3);
+    assert_eq!(broken_at(&verify(&rows).await), 4);
+}
+
+#[tokio::test]
+async fn truncated_tail_past_a_checkpoint_is_detected() {
+    let mut rows = rows(CHECKPOINT_INTERVAL + 5).await;
+    rows.events.truncate(90);
+    let report = verify(&rows).await;
+    assert_eq!(broken_at(&report), CHECKPOINT_INTERVAL);
+}
+
+#[tokio::test]
+async fn deleted_checkpoint_is_detected() {
+    let mut rows = rows(2 * CHECKPOINT_INTERVAL).await;
+    rows.checkpoints.remove(0);
+    let report = verify(&rows).await;
+    assert_eq!(broken_at(&report), CHECKPOINT_INTERVAL);
+    assert!(report.first_broken.unwrap().reason.contains("checkpoint missing"));
+}
+
+#[tokio::test]
+async fn recomputed_chain_fails_at_the_checkpoint() {
+    // without the signing key the hashes can be redone but not the checkpoint
+    let mut rows = rows(CHECKPOINT_INTERVAL + 5).await;
+    rows.events[9].details = Some("{\"forged\":true}".to_string());
+    let mut prev_hash: Option<String> = None;
+    for event in &mut rows.events {
+        let new_event = NewAuditEvent {
+            event: event.event.clone(),
+            actor_id: event.actor_id,
+            subject_id: event.subject_id,
+            subject: event.subject.clone(),
+            ip: event.ip.clone(),
+            user_agent: event.user_agent.clone(),
+            request_id: event.request_id.clone(),
+            details: event.details.clone(),
+        };
+        event.prev_hash = prev_hash;
+        event.hash = Some(chain::event_hash(event.prev_hash.as_deref(), &event.created_at, &new_event));
+        prev_hash = event.hash.clone();
+    }
+    assert_eq!(broken_at(&verify(&rows).await), CHECKPOINT_INTERVAL);
+}
+
+#[tokio::test]
+async fn unhashed_event_inside_the_chain_is_detected() {
+    let mut rows = rows(10).await;
+    rows.events[4].prev_hash = None;
+    rows.events[4].hash = None;
+    rows.events[4].chain_position = None;
+    let report = verify(&rows).await;
+    assert_eq!(broken_at(&report), 5);
+    assert!(report.first_broken.unwrap().reason.contains("no hash"));
+}
+
+#[tokio::test]
+async fn unhashed_events_before_the_chain_are_skipped() {
+    let mut rows = rows(10).await;
+    let mut legacy = rows.events[0].clone();
+    legacy.prev_hash = None;
+    legacy.hash = None;
+    legacy.chain_position = None;
+    for event in &mut rows.events {
+        event.id += 1;
+    }
+    rows.events.insert(0, legacy);
+    let report = verify(&rows).await;
+    assert!(report.is_intact(), "{:?}", report.first_broken);
+    assert_eq!(report.unchained_events, 1);
+    assert_eq!(report.events_checked, 10);
+}
@@ -0,0 +1,45 @@
+//! Which address audit records and token use are attributed to.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use axum_example::common::client_ip;
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+fn proxies() -> Vec<IpNet> {
+    vec!["10.0.0.0/8".parse().unwrap(), IpNet::from(ip("127.0.0.1"))]
+}
+
+#[test]
+fn forwarded_for_from_untrusted_peers_is_ignored() {
+    let peer = ip("203.0.113.7");
+    assert_eq!(client_ip(peer, Some("198.51.100.1"), &proxies()), peer);
+    assert_eq!(client_ip(peer, Some("198.51.100.1"), &[]), peer);
+}
+
+#[test]
+fn trusted_proxy_reports_the_client() {
+    assert_eq!(client_ip(ip("127.0.0.1"), Some("198.51.100.1"), &proxies()), ip("198.51.100.1"));
+    // without the header the proxy itself is the client
+    assert_eq!(client_ip(ip("127.0.0.1"), None, &proxies()), ip("127.0.0.1"));
+}
+
+#[test]
+fn spoofed_hops_left_of_the_client_are_skipped() {
+    let forwarded_for = "1.2.3.4, 198.51.100.1, 10.1.2.3";
+    assert_eq!(client_ip(ip("127.0.0.1"), Some(forwarded_for), &proxies()), ip("198.51.100.1"));
+}
+
+#[test]
+fn mapped_ipv4_peers_match_ipv4_ranges() {
+    assert_eq!(client_ip(ip("::ffff:127.0.0.1"), Some("198.51.100.1"), &proxies()), ip("198.51.100.1"));
+}
+
+#[test]
+fn garbage_hops_stop_the_walk() {
+    assert_eq!(client_ip(ip("10.0.0.1"), Some("198.51.100.1, nonsense"), &proxies()), ip("10.0.0.1"));
+}
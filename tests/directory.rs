@@ -0,0 +1,74 @@
+//! The member directory on in-memory repositories.
+
+use chrono::{Duration, TimeZone, Utc};
+
+use axum_example::config::{self, Config};
+use axum_example::directory::models::{Cursor, DirectoryFilter, DirectorySort};
+use axum_example::profile::models::{NewUser, Visibility};
+use axum_example::state::AppState;
+
+/// `count` verified users, two of them joining in each hour.
+async fn state(count: i32) -> AppState {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    let start = Utc.with_ymd_and_hms(2024, 10, 1, 0, 0, 0).unwrap();
+    for n in 0..count {
+        let new_user = NewUser {
+            email: format!("user{n}@example.com"),
+            username: format!("user{n}"),
+            password: String::new(),
+            is_verify: true,
+            created_at: start + Duration::hours(i64::from(n / 2)),
+        };
+        state.users.create(new_user).await.unwrap();
+    }
+    state
+}
+
+async fn usernames(state: &AppState, filter: &DirectoryFilter) -> Vec<String> {
+    let users = state.users.directory(filter, 100).await.unwrap();
+    users.into_iter().map(|user| user.username).collect()
+}
+
+#[tokio::test]
+async fn keyset_pages_cover_every_user_once() {
+    let state = state(11).await;
+    for sort in [DirectorySort::Newest, DirectorySort::Oldest] {
+        let mut filter = DirectoryFilter { sort, ..Default::default() };
+        let mut seen = Vec::new();
+        loop {
+            let page = state.users.directory(&filter, 3).await.unwrap();
+            let Some(last) = page.last() else { break };
+            filter.after = Some(Cursor::after(last));
+            seen.extend(page.into_iter().map(|user| user.id));
+        }
+        let mut expected: Vec<i32> = (1..=11).collect();
+        if sort == DirectorySort::Newest {
+            expected.reverse();
+        }
+        assert_eq!(seen, expected, "{sort:?}");
+    }
+}
+
+#[tokio::test]
+async fn cursor_round_trips() {
+    let state = state(1).await;
+    let user = state.users.directory(&DirectoryFilter::default(), 1).await.unwrap().remove(0);
+    let cursor = Cursor::after(&user);
+    assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    assert_eq!(Cursor::decode("garbage"), None);
+}
+
+#[tokio::test]
+async fn search_skips_emails_and_hidden_users() {
+    let state = state(3).await;
+    let hidden = state.users.find_by_username("user2").await.unwrap();
+    state.users.set_public_profile(hidden.id, None, Visibility::Hidden).await.unwrap();
+
+    let mut filter = DirectoryFilter { search: Some("USER".to_string()), ..Default::default() };
+    assert_eq!(usernames(&state, &filter).await, ["user1", "user0"]);
+
+    filter.search = Some("example.com".to_string());
+    assert!(usernames(&state, &filter).await.is_empty());
+    filter.search_email = true;
+    assert_eq!(usernames(&state, &filter).await, ["user1", "user0"]);
+}
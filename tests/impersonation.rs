@@ -0,0 +1,89 @@
+//! Impersonation sessions in the browser, on in-memory repositories.
+
+use axum::body::Body;
+use axum::http::header::{COOKIE, LOCATION, SET_COOKIE};
+use axum::http::{Request, StatusCode};
+use axum::middleware::{from_fn, from_fn_with_state};
+use axum::response::Response;
+use chrono::Utc;
+use tower::ServiceExt;
+
+use axum_example::audit::models::AuditFilter;
+use axum_example::auth::middleware::cookie_to_state;
+use axum_example::config::{self, Config};
+use axum_example::i18n::localize;
+use axum_example::profile::models::NewUser;
+use axum_example::routes_admin;
+use axum_example::security::headers::security_headers;
+use axum_example::state::AppState;
+use axum_example::utils::flash::carry_flash;
+use axum_example::utils::jwt::encode_impersonation_jwt;
+
+/// An admin impersonating another user, and the impersonation cookie.
+async fn impersonating() -> (AppState, String) {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    for (email, username) in [("root@example.com", "root"), ("ann@example.com", "ann")] {
+        let new_user = NewUser {
+            email: email.to_string(),
+            username: username.to_string(),
+            password: String::new(),
+            is_verify: true,
+            created_at: Utc::now(),
+        };
+        state.users.create(new_user).await.unwrap();
+    }
+    state.users.set_admin("root@example.com", true).await.unwrap();
+    let admin = state.users.find_by_email("root@example.com").await.unwrap();
+    let target = state.users.find_by_email("ann@example.com").await.unwrap();
+    let token = encode_impersonation_jwt(target.id, target.email, admin.id, 1).await.unwrap();
+    (state, token)
+}
+
+async fn stop(state: &AppState, token: &str) -> Response {
+    let router = routes_admin::build_routes(state.clone())
+        .layer(from_fn_with_state(state.clone(), localize))
+        .layer(from_fn(carry_flash))
+        .layer(from_fn_with_state(state.clone(), security_headers))
+        .layer(from_fn_with_state(state.clone(), cookie_to_state));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/admin/impersonate/stop")
+        .header(COOKIE, format!("visit={token}"))
+        .body(Body::empty())
+        .unwrap();
+    router.oneshot(request).await.unwrap()
+}
+
+/// The session cookie a response sets, if any.
+fn session_cookie(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(|value| value.strip_prefix("visit="))
+        .map(|value| value.split(';').next().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn stopping_logs_the_admin_out() {
+    let (state, token) = impersonating().await;
+    let response = stop(&state, &token).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[LOCATION], "/account/login");
+    assert_eq!(session_cookie(&response).as_deref(), Some(""));
+}
+
+#[tokio::test]
+async fn revoking_the_admins_sessions_ends_the_impersonation() {
+    let (state, token) = impersonating().await;
+    state.sessions.revoke_all("root@example.com").await.unwrap();
+
+    // the cookie no longer opens a session, let alone hands one back
+    let response = stop(&state, &token).await;
+    assert!(response.status().is_redirection());
+    assert_eq!(response.headers()[LOCATION], "/account/login");
+    assert_eq!(session_cookie(&response), None);
+    let filter = AuditFilter { event: Some("impersonation.stop".to_string()), ..Default::default() };
+    assert!(state.audit.events(&filter, 10).await.unwrap().is_empty());
+}
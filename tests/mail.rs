@@ -0,0 +1,34 @@
+//! Outgoing mail written to the development outbox.
+
+use std::time::Duration;
+
+use axum_example::config::{self, Config};
+use axum_example::utils::mail;
+
+#[tokio::test]
+async fn mail_lands_in_the_outbox() {
+    let outbox = std::env::temp_dir().join(format!("outbox-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&outbox).unwrap();
+    config::init(Config {
+        mail_outbox: Some(outbox.clone()),
+        ..Default::default()
+    });
+
+    mail::send(mail::reset_password("eve@example.com", "token123"));
+
+    let mut written = Vec::new();
+    for _ in 0..50 {
+        written = std::fs::read_dir(&outbox).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        if !written.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(written.len(), 1);
+    let message = std::fs::read_to_string(written[0].path()).unwrap();
+    assert!(message.contains("To: eve@example.com"), "{message}");
+    assert!(message.contains("Subject: Reset your password"), "{message}");
+    // undo quoted-printable soft line breaks before looking for the token
+    assert!(message.replace("=\r\n", "").contains("token123"), "{message}");
+    std::fs::remove_dir_all(&outbox).unwrap();
+}
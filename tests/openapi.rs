@@ -0,0 +1,122 @@
+//! Fails when the OpenAPI document and the `/api/v1` routes drift apart.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+use axum_example::api::docs::ApiDoc;
+use axum_example::common::AuthenticatedUser;
+use axum_example::config::Config;
+use axum_example::routes_api;
+use axum_example::state::AppState;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+fn router() -> Router {
+    let state = AppState::in_memory(Arc::new(Config::default())).unwrap();
+    routes_api::build_routes(state)
+}
+
+fn spec_operations() -> BTreeSet<(String, String)> {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let mut operations = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            if METHODS.contains(&method.as_str()) {
+                operations.insert((method.clone(), path.clone()));
+            }
+        }
+    }
+    operations
+}
+
+/// `(method, path)` of every `.route(...)` in `src/routes_api.rs`.
+fn source_operations() -> BTreeSet<(String, String)> {
+    let source = include_str!("../src/routes_api.rs");
+    let mut operations = BTreeSet::new();
+    for call in source.split(".route(").skip(1) {
+        let path = call.split('"').nth(1).unwrap();
+        let handlers = &call[..call.find(".route(").unwrap_or(call.len())];
+        for method in METHODS {
+            if handlers.contains(&format!("{method}(api::handlers::"))
+                || handlers.contains(&format!(".{method}(api::handlers::"))
+            {
+                operations.insert((method.to_string(), format!("/api/v1{path}")));
+            }
+        }
+    }
+    operations
+}
+
+/// Status of a request with a malformed body, optionally as a signed-in
+/// API client so authentication does not mask method routing.
+async fn status(method: &str, path: &str, signed_in: bool) -> StatusCode {
+    let mut request = Request::builder()
+        .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+        .uri(path)
+        .header("content-type", "application/json")
+        .body(Body::from("{"))
+        .unwrap();
+    if signed_in {
+        request.extensions_mut().insert(AuthenticatedUser {
+            id: 1,
+            email: "user@example.com".to_string(),
+            purpose: "auth".to_string(),
+            bearer: true,
+            ..Default::default()
+        });
+    }
+    router().oneshot(request).await.unwrap().status()
+}
+
+#[test]
+fn every_route_is_documented() {
+    let spec = spec_operations();
+    let source = source_operations();
+    assert!(!source.is_empty());
+    let undocumented: Vec<_> = source.difference(&spec).collect();
+    assert!(undocumented.is_empty(), "routes missing from the spec: {undocumented:?}");
+}
+
+#[tokio::test]
+async fn every_documented_operation_is_routed() {
+    for (method, path) in spec_operations() {
+        let status = status(&method, &path, false).await;
+        assert!(
+            status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED,
+            "{} {path} is documented but answers {status}",
+            method.to_uppercase(),
+        );
+    }
+}
+
+#[tokio::test]
+async fn undocumented_methods_are_not_routed() {
+    let spec = spec_operations();
+    let paths: BTreeSet<_> = spec.iter().map(|(_, path)| path.clone()).collect();
+    for path in paths {
+        for method in METHODS {
+            if spec.contains(&(method.to_string(), path.clone())) {
+                continue;
+            }
+            assert_eq!(
+                status(method, &path, true).await,
+                StatusCode::METHOD_NOT_ALLOWED,
+                "{} {path} is routed but not documented",
+                method.to_uppercase(),
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn spec_is_served() {
+    let request = Request::builder().uri("/api/openapi.json").body(Body::empty()).unwrap();
+    let response = router().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
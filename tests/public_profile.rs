@@ -0,0 +1,54 @@
+//! Who gets to see `/u/{username}`, on in-memory repositories.
+
+use chrono::Utc;
+
+use axum_example::common::AuthenticatedUser;
+use axum_example::config::{self, Config};
+use axum_example::profile::models::{NewUser, Visibility};
+use axum_example::state::AppState;
+
+fn viewer(id: i32) -> AuthenticatedUser {
+    AuthenticatedUser {
+        id,
+        email: format!("user{id}@example.com"),
+        purpose: "auth".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn visibility_rules() {
+    let guest = AuthenticatedUser::default();
+    let (owner, member) = (viewer(1), viewer(2));
+
+    assert!(Visibility::Public.allows(1, &guest));
+    assert!(!Visibility::Members.allows(1, &guest));
+    assert!(Visibility::Members.allows(1, &member));
+    assert!(!Visibility::Hidden.allows(1, &member));
+    assert!(Visibility::Hidden.allows(1, &owner));
+    // guests have id 0, which must not count as owning anything
+    assert!(!Visibility::Hidden.allows(0, &guest));
+}
+
+#[tokio::test]
+async fn bio_and_visibility_are_saved() {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    let new_user = NewUser {
+        email: "eve@example.com".to_string(),
+        username: "eve".to_string(),
+        password: String::new(),
+        is_verify: true,
+        created_at: Utc::now(),
+    };
+    state.users.create(new_user).await.unwrap();
+
+    let user = state.users.find_by_username("eve").await.unwrap();
+    assert_eq!(user.visibility, Visibility::Members);
+    assert_eq!(user.bio, None);
+
+    state.users.set_public_profile(user.id, Some("hello"), Visibility::Hidden).await.unwrap();
+    let user = state.users.find_by_username("eve").await.unwrap();
+    assert_eq!(user.visibility, Visibility::Hidden);
+    assert_eq!(user.bio.as_deref(), Some("hello"));
+    assert!(state.users.find_by_username("Eve").await.is_err());
+}
@@ -0,0 +1,50 @@
+//! Session tokens last as long as the configured session cookie.
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use chrono::Utc;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use axum_example::config::{self, Config};
+use axum_example::profile::models::NewUser;
+use axum_example::routes_api;
+use axum_example::state::AppState;
+use axum_example::utils::date_option::get_max_age_seconds;
+use axum_example::utils::jwt::{ar_hash_password, decode_token, encode_session_after};
+
+const HOURS: i64 = 48;
+
+#[tokio::test]
+async fn tokens_expire_with_the_cookie() {
+    let state = AppState::in_memory(config::init(Config { max_age_cookie: HOURS, ..Default::default() })).unwrap();
+    let new_user = NewUser {
+        email: "ann@example.com".to_string(),
+        username: "ann".to_string(),
+        password: ar_hash_password("password123").unwrap(),
+        is_verify: true,
+        created_at: Utc::now(),
+    };
+    state.users.create(new_user).await.unwrap();
+
+    let login = json!({ "email": "ann@example.com", "password": "password123" });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/login")
+        .header("content-type", "application/json")
+        .body(Body::from(login.to_string()))
+        .unwrap();
+    let response = routes_api::build_routes(state.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["expires_in"], HOURS * 3600);
+    assert_eq!(get_max_age_seconds(), (HOURS * 3600).to_string());
+
+    let login = body["token"].as_str().unwrap().to_string();
+    let renewed = encode_session_after("ann@example.com".to_string(), Utc::now()).await.unwrap();
+    for token in [login, renewed] {
+        let claims = decode_token(token).await.unwrap().unwrap();
+        assert_eq!((claims.exp - claims.iat) as i64, HOURS * 3600);
+    }
+}
@@ -0,0 +1,61 @@
+//! Request spans exported over OTLP/HTTP, to a stub collector.
+
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::Request;
+use axum::routing::{get, post};
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+use tower_http::trace::TraceLayer;
+
+use axum_example::config::LogFormat;
+use axum_example::telemetry::{self, request_span};
+
+/// Accepts trace exports and hands their bodies to the test.
+async fn collector() -> (String, mpsc::UnboundedReceiver<Bytes>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route(
+            "/v1/traces",
+            post(|State(sender): State<mpsc::UnboundedSender<Bytes>>, body: Bytes| async move {
+                // an empty body is an empty ExportTraceServiceResponse
+                let _ = sender.send(body);
+            }),
+        )
+        .with_state(sender);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (endpoint, receiver)
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle.as_bytes())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_spans_reach_the_collector() {
+    let (endpoint, mut exports) = collector().await;
+    let telemetry = telemetry::init(LogFormat::Pretty, Some(&endpoint));
+
+    let app = Router::new()
+        .route("/otel-probe", get(|| async { "ok" }))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span));
+    let request = Request::builder().uri("/otel-probe").body(Body::empty()).unwrap();
+    app.oneshot(request).await.unwrap();
+
+    // shutting the provider down flushes the batch, and blocks while it does
+    tokio::task::spawn_blocking(move || drop(telemetry)).await.unwrap();
+
+    let export = tokio::time::timeout(Duration::from_secs(10), exports.recv())
+        .await
+        .expect("no trace export within 10s")
+        .unwrap();
+    assert!(contains(&export, "request"), "span name missing");
+    assert!(contains(&export, "/otel-probe"), "path attribute missing");
+    assert!(contains(&export, env!("CARGO_PKG_NAME")), "service name missing");
+}
@@ -0,0 +1,106 @@
+//! Values from requests and users are escaped in every template.
+
+use axum::extract::{Query, State};
+use chrono::Utc;
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use axum_example::admin::handlers::users;
+use axum_example::audit::handlers::audit_events;
+use axum_example::audit::models::{AuditFilter, NewAuditEvent};
+use axum_example::common::{page_context, AuthenticatedUser, Impersonator, RequestMeta, Templates};
+use axum_example::config::{self, Config};
+use axum_example::i18n::Locale;
+use axum_example::profile::models::NewUser;
+use axum_example::state::AppState;
+
+const SCRIPT: &str = "<script>alert(1)</script>";
+/// Short enough to pass username validation.
+const SVG: &str = "<svg/onload=alert()>";
+
+fn admin() -> AuthenticatedUser {
+    AuthenticatedUser {
+        id: 1,
+        email: "admin@example.com".to_string(),
+        username: "admin".to_string(),
+        purpose: "auth".to_string(),
+        is_admin: true,
+        ..Default::default()
+    }
+}
+
+fn templates(state: &AppState) -> Templates {
+    Templates::new(state.templates.clone(), Locale::parse("en").unwrap(), Default::default(), Default::default())
+}
+
+async fn body(response: impl IntoResponse) -> String {
+    let body = axum::body::to_bytes(response.into_response().into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+async fn audit_page(state: &AppState, filter: AuditFilter) -> String {
+    let response = audit_events(State(state.clone()), Extension(templates(state)), Query(filter), admin())
+        .await
+        .unwrap();
+    body(response).await
+}
+
+#[tokio::test]
+async fn audit_page_escapes_recorded_values() {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    let meta = RequestMeta {
+        user_agent: Some(SCRIPT.to_string()),
+        request_id: Some(SCRIPT.to_string()),
+        ..Default::default()
+    };
+    let event = NewAuditEvent::new("login.failed", &meta).subject(SCRIPT);
+    state.audit.append(&event).await.unwrap();
+
+    let page = audit_page(&state, AuditFilter::default()).await;
+    assert!(!page.contains("<script>alert"), "{page}");
+    assert_eq!(page.matches("&lt;script&gt;alert(1)&lt;&#x2F;script&gt;").count(), 3, "{page}");
+
+    // the filter is echoed back into the form
+    let filter = AuditFilter { subject: Some(format!("\">{SCRIPT}")), ..Default::default() };
+    let page = audit_page(&state, filter).await;
+    assert!(!page.contains("<script>alert"), "{page}");
+    assert!(page.contains("value=\"&quot;&gt;&lt;script&gt;"), "{page}");
+}
+
+#[tokio::test]
+async fn usernames_are_escaped_on_admin_pages() {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    let new_user = NewUser {
+        email: "eve@example.com".to_string(),
+        username: SVG.to_string(),
+        password: String::new(),
+        is_verify: true,
+        created_at: Utc::now(),
+    };
+    state.users.create(new_user).await.unwrap();
+    let page = body(users(State(state.clone()), Extension(templates(&state)), admin()).await.unwrap()).await;
+    assert!(!page.contains(SVG), "{page}");
+    assert!(page.contains("&lt;svg&#x2F;onload=alert()&gt;"), "{page}");
+
+    // the banner an impersonated user sees
+    let user = AuthenticatedUser {
+        id: 2,
+        email: "eve@example.com".to_string(),
+        username: SVG.to_string(),
+        purpose: "impersonate".to_string(),
+        impersonator: Some(Impersonator { id: 1, email: "admin@example.com".to_string(), username: "admin".to_string() }),
+        ..Default::default()
+    };
+    let page = templates(&state).render("index", &page_context(&user)).unwrap();
+    assert!(!page.contains(SVG), "{page}");
+    assert!(page.contains("&lt;svg&#x2F;onload=alert()&gt;"), "{page}");
+    // arguments of t() are escaped once, with its output
+    assert!(!page.contains("&amp;lt;"), "{page}");
+}
+
+#[test]
+fn asset_urls_are_not_entity_encoded() {
+    let state = AppState::in_memory(config::init(Config::default())).unwrap();
+    let page = templates(&state).render("index", &page_context(&AuthenticatedUser::default())).unwrap();
+    assert!(page.contains("href=\"/assets/css/style."), "{page}");
+}